@@ -0,0 +1,34 @@
+// Bundles every file in `patterns/` and `rules/` into the binary.
+// Adding a pattern or a rule is just adding a file there.
+
+use std::fs;
+use std::path::Path;
+
+fn main() {
+    bundle("patterns", "rle", "BUNDLED_PATTERNS", "patterns.rs");
+    bundle("rules", "rule", "BUNDLED_RULES", "rules.rs");
+}
+
+// Writes `const_name`, a list of (file name, contents), to `out_file`.
+fn bundle(folder: &str, extension: &str, const_name: &str, out_file: &str) {
+    println!("cargo:rerun-if-changed={folder}");
+
+    let dir = Path::new(&std::env::var("CARGO_MANIFEST_DIR").unwrap()).join(folder);
+    let mut files: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == extension))
+        .collect();
+    files.sort();
+
+    let mut code = format!("pub const {const_name}: &[(&str, &str)] = &[\n");
+    for path in files {
+        let name = path.file_name().unwrap().to_string_lossy();
+        code.push_str(&format!("    ({name:?}, include_str!({:?})),\n", path.display().to_string()));
+    }
+    code.push_str("];\n");
+
+    let out = Path::new(&std::env::var("OUT_DIR").unwrap()).join(out_file);
+    fs::write(out, code).unwrap();
+}
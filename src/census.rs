@@ -0,0 +1,212 @@
+// Object census, in the spirit of apgsearch.
+//
+// The live cells are split into islands (cells touching each other, also
+// diagonally) and every island is looked up in the pattern catalog. To find
+// an island in any position, both sides are turned into a canonical shape:
+// of the 8 rotations and reflections, the one whose sorted cell list is the
+// smallest. Oscillators and spaceships are added once per phase.
+//
+// Objects made of several islands (a pulsar, some phases of a beacon) are
+// not recognised and show up as unidentified.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::pattern::{self, Category};
+use crate::plane::Plane;
+use crate::{Cell, Vectrix};
+
+// Oscillators with a longer period are only looked up in the first phases.
+const MAX_PHASES: u32 = 64;
+
+type Shape = Vec<(i64, i64)>;
+type Transform = fn(i64, i64) -> (i64, i64);
+
+pub struct Census {
+    // Object name and how many, most common first.
+    pub counts: Vec<(String, usize)>,
+}
+
+impl Census {
+    pub fn take(map: &Vectrix) -> Census {
+        let known = known_shapes();
+        let mut counts = HashMap::<String, usize>::new();
+
+        for island in torus_islands(map) {
+            let name = match known.get(&canonical(&island)) {
+                Some(name) => name.clone(),
+                None => format!("unidentified ({} cells)", island.len()),
+            };
+            *counts.entry(name).or_insert(0) += 1;
+        }
+
+        let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        Census { counts }
+    }
+
+    pub fn describe(&self) -> String {
+        if self.counts.is_empty() {
+            return String::from("Census: no objects.");
+        }
+        let objects: Vec<String> = self
+            .counts
+            .iter()
+            .map(|(name, count)| format!("{count} {name}"))
+            .collect();
+        format!("Census: {}.", objects.join(", "))
+    }
+}
+
+// Canonical shape of every phase of every still life, oscillator and
+// spaceship in the catalog.
+fn known_shapes() -> HashMap<Shape, String> {
+    let (patterns, _) = pattern::full_catalog();
+    let mut known = HashMap::<Shape, String>::new();
+
+    for pattern in patterns {
+        let phases = match pattern.category {
+            Category::StillLife => 1,
+            Category::Oscillator | Category::Spaceship => pattern.period.unwrap_or(1).min(MAX_PHASES),
+            _ => continue,
+        };
+
+        let mut plane = Plane::from_points(&pattern.cells);
+        for _ in 0..phases {
+            let cells: Shape = plane.cells.iter().copied().collect();
+            if is_connected(&cells) {
+                // First one wins, the catalog is sorted by category and name.
+                known.entry(canonical(&cells)).or_insert_with(|| pattern.name.clone());
+            }
+            plane = plane.step();
+        }
+    }
+    known
+}
+
+fn canonical(cells: &[(i64, i64)]) -> Shape {
+    let transforms: [Transform; 8] = [
+        |r, c| (r, c),
+        |r, c| (c, -r),
+        |r, c| (-r, -c),
+        |r, c| (-c, r),
+        |r, c| (r, -c),
+        |r, c| (-r, c),
+        |r, c| (c, r),
+        |r, c| (-c, -r),
+    ];
+
+    transforms
+        .iter()
+        .map(|transform| {
+            let moved: Vec<(i64, i64)> = cells.iter().map(|(r, c)| transform(*r, *c)).collect();
+            let top = moved.iter().map(|(r, _)| *r).min().unwrap_or(0);
+            let left = moved.iter().map(|(_, c)| *c).min().unwrap_or(0);
+            let mut shape: Shape = moved.iter().map(|(r, c)| (r - top, c - left)).collect();
+            shape.sort();
+            shape
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+fn is_connected(cells: &[(i64, i64)]) -> bool {
+    let all: HashSet<(i64, i64)> = cells.iter().copied().collect();
+    let Some(first) = cells.first() else {
+        return false;
+    };
+
+    let mut seen = HashSet::from([*first]);
+    let mut queue = VecDeque::from([*first]);
+    while let Some((row, col)) = queue.pop_front() {
+        for d_row in -1..=1 {
+            for d_col in -1..=1 {
+                let next = (row + d_row, col + d_col);
+                if all.contains(&next) && seen.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+    seen.len() == all.len()
+}
+
+// Islands of the map. The map wraps, so an island crossing an edge is
+// unwrapped: its cells get coordinates relative to the first one found,
+// possibly negative or past the edge.
+fn torus_islands(map: &Vectrix) -> Vec<Shape> {
+    let rows = map.len() as i64;
+    let cols = map[0].len() as i64;
+    let mut seen = vec![vec![false; cols as usize]; rows as usize];
+    let mut islands = Vec::<Shape>::new();
+
+    for i in 0..rows {
+        for j in 0..cols {
+            if seen[i as usize][j as usize] || !matches!(map[i as usize][j as usize], Cell::Alive(..)) {
+                continue;
+            }
+
+            seen[i as usize][j as usize] = true;
+            let mut island: Shape = Vec::new();
+            let mut queue = VecDeque::from([(i, j)]);
+            while let Some((row, col)) = queue.pop_front() {
+                island.push((row, col));
+                for d_row in -1..=1 {
+                    for d_col in -1..=1 {
+                        let (next_row, next_col) = (row + d_row, col + d_col);
+                        let (wrapped_row, wrapped_col) =
+                            (next_row.rem_euclid(rows) as usize, next_col.rem_euclid(cols) as usize);
+                        if !seen[wrapped_row][wrapped_col] && matches!(map[wrapped_row][wrapped_col], Cell::Alive(..)) {
+                            seen[wrapped_row][wrapped_col] = true;
+                            queue.push_back((next_row, next_col));
+                        }
+                    }
+                }
+            }
+            islands.push(island);
+        }
+    }
+    islands
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A map from rows like "x..", x is alive.
+    fn map_of(rows: &[&str]) -> Vectrix {
+        rows.iter()
+            .map(|row| row.chars().map(|ch| if ch == 'x' { Cell::alive() } else { Cell::dead() }).collect())
+            .collect()
+    }
+
+    #[test]
+    fn counts_known_and_unknown_objects() {
+        // A block across the corner, a blinker, two gliders facing
+        // different ways and an L of three cells.
+        let map = map_of(&[
+            "x..........x",
+            "............",
+            "............",
+            "..x....x....",
+            "..x.....x...",
+            "..x...xxx...",
+            "............",
+            "............",
+            ".xx....x....",
+            ".x....x.....",
+            "......xxx...",
+            "x..........x",
+        ]);
+        let census = Census::take(&map);
+        let counts: Vec<(&str, usize)> = census.counts.iter().map(|(name, count)| (name.as_str(), *count)).collect();
+        assert_eq!(counts, [("Glider", 2), ("Blinker", 1), ("Block", 1), ("unidentified (3 cells)", 1)]);
+        assert_eq!(census.describe(), "Census: 2 Glider, 1 Blinker, 1 Block, 1 unidentified (3 cells).");
+    }
+
+    #[test]
+    fn empty_map_has_no_objects() {
+        let census = Census::take(&map_of(&["....", "...."]));
+        assert!(census.counts.is_empty());
+        assert_eq!(census.describe(), "Census: no objects.");
+    }
+}
@@ -0,0 +1,341 @@
+// Rectangular selections and the clipboard used to copy, cut and paste them.
+//
+// The clipboard keeps the cells that aren't dead as a list of `Coordinates`
+// relative to the top left corner of the copied region, the same kind of
+// point list `Map::generate_pattern` stamps on the map. Each point keeps its
+// cell too, so colours and dying states are pasted back as they were.
+
+use crate::{Cell, Coordinates, Vectrix};
+
+#[derive(Clone)]
+pub struct Selection {
+    pub top: usize,
+    pub left: usize,
+    pub bottom: usize,
+    pub right: usize,
+}
+
+impl Selection {
+    // Corners may be given in any order. They are clamped to the map.
+    pub fn new(map: &Vectrix, corner_a: Coordinates, corner_b: Coordinates) -> Selection {
+        let row_last = map.len() - 1;
+        let col_last = map[0].len() - 1;
+
+        Selection {
+            top: corner_a.row.min(corner_b.row).min(row_last),
+            left: corner_a.col.min(corner_b.col).min(col_last),
+            bottom: corner_a.row.max(corner_b.row).min(row_last),
+            right: corner_a.col.max(corner_b.col).min(col_last),
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.bottom - self.top + 1
+    }
+
+    pub fn cols(&self) -> usize {
+        self.right - self.left + 1
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "rows {}-{}, cols {}-{} ({} x {})",
+            self.top, self.bottom, self.left, self.right, self.rows(), self.cols()
+        )
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum PasteMode {
+    // Live cells are added, nothing is removed.
+    Or,
+    // Live cells toggle what is under them.
+    Xor,
+    // The whole region is replaced, dead cells included.
+    Overwrite,
+}
+
+impl PasteMode {
+    pub fn next(&self) -> PasteMode {
+        match self {
+            PasteMode::Or => PasteMode::Xor,
+            PasteMode::Xor => PasteMode::Overwrite,
+            PasteMode::Overwrite => PasteMode::Or,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            PasteMode::Or => "OR",
+            PasteMode::Xor => "XOR",
+            PasteMode::Overwrite => "Overwrite",
+        }
+    }
+}
+
+pub struct Clipboard {
+    pub rows: usize,
+    pub cols: usize,
+    pub points: Vec<Coordinates>,
+    // The cell at each of `points`.
+    pub cells: Vec<Cell>,
+}
+
+impl Clipboard {
+    pub fn copy(map: &Vectrix, selection: &Selection) -> Clipboard {
+        let mut points = Vec::<Coordinates>::new();
+        let mut cells = Vec::<Cell>::new();
+
+        for (i, row) in map[selection.top..=selection.bottom].iter().enumerate() {
+            for (j, cell) in row[selection.left..=selection.right].iter().enumerate() {
+                if !matches!(cell, Cell::Dead(_)) {
+                    points.push(Coordinates::new(i, j));
+                    cells.push(cell.clone());
+                }
+            }
+        }
+
+        Clipboard {
+            rows: selection.rows(),
+            cols: selection.cols(),
+            points,
+            cells,
+        }
+    }
+
+    // Any list of points, e.g. a pattern, all alive. Also returns the top left
+    // corner of the points, where the clipboard would paste them back.
+    pub fn from_points(points: Vec<Coordinates>) -> (Clipboard, Coordinates) {
+        let top = points.iter().map(|point| point.row).min().unwrap_or(0);
+        let left = points.iter().map(|point| point.col).min().unwrap_or(0);
+        let bottom = points.iter().map(|point| point.row).max().unwrap_or(0);
+        let right = points.iter().map(|point| point.col).max().unwrap_or(0);
+
+        let clipboard = Clipboard {
+            rows: bottom - top + 1,
+            cols: right - left + 1,
+            cells: vec![Cell::alive(); points.len()],
+            points: points
+                .iter()
+                .map(|point| Coordinates::new(point.row - top, point.col - left))
+                .collect(),
+        };
+        (clipboard, Coordinates::new(top, left))
+    }
+
+    // Copy the region and then kill every cell in it.
+    pub fn cut(map: &mut Vectrix, selection: &Selection) -> Clipboard {
+        let clipboard = Clipboard::copy(map, selection);
+        for row in &mut map[selection.top..=selection.bottom] {
+            for cell in &mut row[selection.left..=selection.right] {
+                *cell = Cell::dead();
+            }
+        }
+        clipboard
+    }
+
+    // 90 degrees clockwise.
+    pub fn rotate(&mut self) {
+        for point in self.points.iter_mut() {
+            *point = Coordinates::new(point.col, self.rows - 1 - point.row);
+        }
+        (self.rows, self.cols) = (self.cols, self.rows);
+    }
+
+    // Mirror left to right.
+    pub fn flip_horizontal(&mut self) {
+        for point in self.points.iter_mut() {
+            point.col = self.cols - 1 - point.col;
+        }
+    }
+
+    // Mirror top to bottom.
+    pub fn flip_vertical(&mut self) {
+        for point in self.points.iter_mut() {
+            point.row = self.rows - 1 - point.row;
+        }
+    }
+
+    // The points of the live cells only, e.g. for a Life pattern.
+    pub fn live_points(&self) -> Vec<Coordinates> {
+        self.points
+            .iter()
+            .zip(&self.cells)
+            .filter(|(_, cell)| matches!(cell, Cell::Alive(..)))
+            .map(|(point, _)| point.clone())
+            .collect()
+    }
+
+    // Points moved to `origin`, ready for `Map::generate_pattern`.
+    pub fn points_at(&self, origin: &Coordinates) -> Vec<Coordinates> {
+        self.points
+            .iter()
+            .map(|point| Coordinates::new(origin.row + point.row, origin.col + point.col))
+            .collect()
+    }
+
+    pub fn paste(&self, map: &mut Vectrix, origin: &Coordinates, mode: PasteMode) -> String {
+        let row_len = map.len();
+        let col_len = map[0].len();
+
+        let points = self.points_at(origin);
+        let cells = points.iter().map(|point| (point.row % row_len, point.col % col_len)).zip(&self.cells);
+
+        match mode {
+            PasteMode::Or => {
+                for ((row, col), cell) in cells {
+                    map[row][col] = cell.clone();
+                }
+            },
+            PasteMode::Xor => {
+                for ((row, col), cell) in cells {
+                    map[row][col] = match map[row][col] {
+                        Cell::Dead(_) => cell.clone(),
+                        _ => Cell::dead(),
+                    };
+                }
+            },
+            PasteMode::Overwrite => {
+                for i in 0..self.rows {
+                    for j in 0..self.cols {
+                        map[(origin.row + i) % row_len][(origin.col + j) % col_len] = Cell::dead();
+                    }
+                }
+                for ((row, col), cell) in cells {
+                    map[row][col] = cell.clone();
+                }
+            },
+        }
+
+        format!("[+] Pasted {} x {} region ({}).", self.rows, self.cols, mode.name())
+    }
+
+    // Just the clipboard, as a map of its own.
+    pub fn to_map(&self) -> Vectrix {
+        let mut map = vec![vec![Cell::dead(); self.cols]; self.rows];
+        for (point, cell) in self.points.iter().zip(&self.cells) {
+            map[point.row][point.col] = cell.clone();
+        }
+        map
+    }
+
+    // The map with the clipboard drawn on top, to see where it would go.
+    pub fn preview(&self, map: &Vectrix, origin: &Coordinates) -> Vectrix {
+        let mut preview = map.clone();
+        let row_len = map.len();
+        let col_len = map[0].len();
+
+        for point in self.points_at(origin) {
+            preview[point.row % row_len][point.col % col_len] = Cell::Alive(0, String::from("▒"));
+        }
+        preview
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A map from rows like "x..", x is alive.
+    fn map_of(rows: &[&str]) -> Vectrix {
+        rows.iter()
+            .map(|row| row.chars().map(|ch| if ch == 'x' { Cell::alive() } else { Cell::dead() }).collect())
+            .collect()
+    }
+
+    fn rows_of(map: &Vectrix) -> Vec<String> {
+        map.iter()
+            .map(|row| row.iter().map(|cell| if let Cell::Alive(..) = cell { 'x' } else { '.' }).collect())
+            .collect()
+    }
+
+    fn whole(map: &Vectrix) -> Selection {
+        Selection::new(map, Coordinates::new(0, 0), Coordinates::new(map.len() - 1, map[0].len() - 1))
+    }
+
+    #[test]
+    fn corners_in_any_order_are_clamped() {
+        let map = map_of(&["....", "....", "...."]);
+        let selection = Selection::new(&map, Coordinates::new(9, 1), Coordinates::new(1, 0));
+        assert_eq!((selection.top, selection.left, selection.bottom, selection.right), (1, 0, 2, 1));
+        assert_eq!((selection.rows(), selection.cols()), (2, 2));
+    }
+
+    #[test]
+    fn cut_and_paste_back() {
+        let mut map = map_of(&["x..", ".x.", "..."]);
+        let selection = Selection::new(&map, Coordinates::new(0, 0), Coordinates::new(1, 1));
+        let clipboard = Clipboard::cut(&mut map, &selection);
+        assert_eq!(rows_of(&map), ["...", "...", "..."]);
+
+        // Past the edge wraps around the torus.
+        clipboard.paste(&mut map, &Coordinates::new(2, 2), PasteMode::Or);
+        assert_eq!(rows_of(&map), ["x..", "...", "..x"]);
+    }
+
+    #[test]
+    fn rotate_and_flip() {
+        let map = map_of(&["xx.", "x.."]);
+        let mut clipboard = Clipboard::copy(&map, &whole(&map));
+
+        clipboard.rotate();
+        let mut rotated = map_of(&["..", "..", ".."]);
+        clipboard.paste(&mut rotated, &Coordinates::new(0, 0), PasteMode::Or);
+        assert_eq!(rows_of(&rotated), ["xx", ".x", ".."]);
+
+        clipboard.flip_horizontal();
+        clipboard.flip_vertical();
+        let mut flipped = map_of(&["..", "..", ".."]);
+        clipboard.paste(&mut flipped, &Coordinates::new(0, 0), PasteMode::Or);
+        assert_eq!(rows_of(&flipped), ["..", "x.", "xx"]);
+    }
+
+    #[test]
+    fn paste_modes() {
+        let clipboard = Clipboard::copy(&map_of(&["x.", ".x"]), &whole(&map_of(&["x.", ".x"])));
+        let start = map_of(&["xx.", "...", "..."]);
+
+        let mut or = start.clone();
+        clipboard.paste(&mut or, &Coordinates::new(0, 0), PasteMode::Or);
+        assert_eq!(rows_of(&or), ["xx.", ".x.", "..."]);
+
+        let mut xor = start.clone();
+        clipboard.paste(&mut xor, &Coordinates::new(0, 0), PasteMode::Xor);
+        assert_eq!(rows_of(&xor), [".x.", ".x.", "..."]);
+
+        let mut overwrite = start.clone();
+        clipboard.paste(&mut overwrite, &Coordinates::new(0, 0), PasteMode::Overwrite);
+        assert_eq!(rows_of(&overwrite), ["x..", ".x.", "..."]);
+    }
+
+    #[test]
+    fn colours_and_states_paste_back() {
+        let mut map = map_of(&["...", "...", "..."]);
+        map[0][0] = Cell::coloured(2);
+        map[0][1] = Cell::dying(3);
+        map[1][1] = Cell::alive();
+        let clipboard = Clipboard::copy(&map, &Selection::new(&map, Coordinates::new(0, 0), Coordinates::new(1, 1)));
+        assert_eq!(clipboard.points.len(), 3);
+        assert_eq!(clipboard.live_points().len(), 2);
+
+        for mode in [PasteMode::Or, PasteMode::Xor, PasteMode::Overwrite] {
+            let mut pasted = map_of(&["...", "...", "..."]);
+            clipboard.paste(&mut pasted, &Coordinates::new(1, 1), mode);
+            assert_eq!(pasted[1][1].colour(), 2);
+            assert_eq!(pasted[1][2].state(), 3);
+            assert!(pasted[2][2] == Cell::alive());
+        }
+    }
+
+    #[test]
+    fn points_become_a_clipboard() {
+        let points = vec![Coordinates::new(5, 7), Coordinates::new(6, 8), Coordinates::new(7, 6)];
+        let (clipboard, corner) = Clipboard::from_points(points);
+        assert_eq!((corner.row, corner.col), (5, 6));
+        assert_eq!(rows_of(&clipboard.to_map()), [".x.", "..x", "x.."]);
+
+        // Pasted back at its corner, the points are where they were.
+        let moved: Vec<(usize, usize)> = clipboard.points_at(&corner).iter().map(|point| (point.row, point.col)).collect();
+        assert_eq!(moved, [(5, 7), (6, 8), (7, 6)]);
+    }
+}
@@ -0,0 +1,226 @@
+// Cycle detection for the play loop.
+//
+// The last few generations are remembered. When the current generation is
+// the same as one of them the universe is in a cycle, and the distance
+// between both is the period.
+//
+// The map is a torus, so a spaceship comes back to where it started only
+// after crossing the whole map. To catch it earlier, generations are also
+// compared shifted. Every generation gets a key that doesn't change when the
+// map is shifted (population and the sorted row and column populations),
+// and only generations with the same key are compared cell by cell.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+
+use crate::{Cell, Vectrix};
+
+pub const DEFAULT_WINDOW: usize = 256;
+
+pub struct Cycle {
+    // First generation of the cycle.
+    pub start: u32,
+    pub period: u32,
+    // How far the universe moved in one period (rows, cols).
+    pub shift: (i64, i64),
+}
+
+impl Cycle {
+    pub fn describe(&self) -> String {
+        let mut text = format!("Cycle of period {} since generation {}", self.period, self.start);
+        if self.shift != (0, 0) {
+            text.push_str(&format!(", moving {:?} per period", self.shift));
+        }
+        text.push('.');
+        text
+    }
+}
+
+struct Snapshot {
+    generation: u32,
+    key: u64,
+    row_counts: Vec<usize>,
+    col_counts: Vec<usize>,
+    // Cell states, so dying cells of Generations rules count too.
+    cells: Vec<Vec<u8>>,
+}
+
+impl Snapshot {
+    fn new(generation: u32, map: &Vectrix) -> Snapshot {
+        let cells: Vec<Vec<u8>> = map
+            .iter()
+            .map(|row| row.iter().map(Cell::state).collect())
+            .collect();
+
+        let row_counts: Vec<usize> = cells
+            .iter()
+            .map(|row| row.iter().filter(|state| **state != 0).count())
+            .collect();
+        let col_counts: Vec<usize> = (0..cells[0].len())
+            .map(|j| cells.iter().filter(|row| row[j] != 0).count())
+            .collect();
+
+        let mut sorted_rows = row_counts.clone();
+        let mut sorted_cols = col_counts.clone();
+        sorted_rows.sort();
+        sorted_cols.sort();
+        let mut hasher = DefaultHasher::new();
+        sorted_rows.hash(&mut hasher);
+        sorted_cols.hash(&mut hasher);
+
+        Snapshot {
+            generation,
+            key: hasher.finish(),
+            row_counts,
+            col_counts,
+            cells,
+        }
+    }
+
+    // Shift (rows, cols) that turns `older` into this snapshot, if any.
+    fn shift_from(&self, older: &Snapshot) -> Option<(usize, usize)> {
+        if self.key != older.key {
+            return None;
+        }
+        let rows = self.cells.len();
+        let cols = self.cells[0].len();
+
+        let row_shifts = matching_shifts(&self.row_counts, &older.row_counts);
+        let col_shifts = matching_shifts(&self.col_counts, &older.col_counts);
+
+        for d_row in &row_shifts {
+            for d_col in &col_shifts {
+                let same = (0..rows).all(|i| {
+                    (0..cols).all(|j| {
+                        self.cells[i][j] == older.cells[(i + rows - d_row) % rows][(j + cols - d_col) % cols]
+                    })
+                });
+                if same {
+                    return Some((*d_row, *d_col));
+                }
+            }
+        }
+        None
+    }
+}
+
+// Every rotation of `older` that gives `current`.
+fn matching_shifts(current: &[usize], older: &[usize]) -> Vec<usize> {
+    let len = current.len();
+    (0..len)
+        .filter(|shift| (0..len).all(|i| current[i] == older[(i + len - shift) % len]))
+        .collect()
+}
+
+pub struct CycleDetector {
+    window: usize,
+    history: VecDeque<Snapshot>,
+}
+
+impl CycleDetector {
+    pub fn new(window: usize) -> CycleDetector {
+        CycleDetector {
+            window: window.max(1),
+            history: VecDeque::new(),
+        }
+    }
+
+    // Call once per generation. Returns the cycle the first time one is found.
+    pub fn observe(&mut self, generation: u32, map: &Vectrix) -> Option<Cycle> {
+        let snapshot = Snapshot::new(generation, map);
+        let rows = map.len() as i64;
+        let cols = map[0].len() as i64;
+
+        // Newest first, so the shortest period wins.
+        let mut found = None;
+        for older in self.history.iter().rev() {
+            if let Some((d_row, d_col)) = snapshot.shift_from(older) {
+                found = Some(Cycle {
+                    start: older.generation,
+                    period: generation - older.generation,
+                    shift: (signed_shift(d_row as i64, rows), signed_shift(d_col as i64, cols)),
+                });
+                break;
+            }
+        }
+
+        self.history.push_back(snapshot);
+        if self.history.len() > self.window {
+            self.history.pop_front();
+        }
+        found
+    }
+}
+
+// A shift of 9 on a torus of 10 is really a shift of -1.
+fn signed_shift(shift: i64, size: i64) -> i64 {
+    if shift > size / 2 {
+        shift - size
+    } else {
+        shift
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::next_generation;
+    use crate::rule::Rule;
+
+    // A map from rows like "x..", x is alive.
+    fn map_of(rows: &[&str]) -> Vectrix {
+        rows.iter()
+            .map(|row| row.chars().map(|ch| if ch == 'x' { Cell::alive() } else { Cell::dead() }).collect())
+            .collect()
+    }
+
+    // Plays `map` until the detector finds a cycle, for at most `limit` generations.
+    fn first_cycle(map: &Vectrix, limit: u32) -> Option<Cycle> {
+        let mut detector = CycleDetector::new(DEFAULT_WINDOW);
+        let mut map = map.clone();
+        for generation in 0..=limit {
+            if let Some(cycle) = detector.observe(generation, &map) {
+                return Some(cycle);
+            }
+            map = next_generation(&map, &Rule::life());
+        }
+        None
+    }
+
+    #[test]
+    fn blinker_has_period_two() {
+        let blinker = map_of(&[".....", ".....", ".xxx.", ".....", "....."]);
+        let cycle = first_cycle(&blinker, 10).unwrap();
+        assert_eq!((cycle.start, cycle.period, cycle.shift), (0, 2, (0, 0)));
+    }
+
+    #[test]
+    fn glider_repeats_shifted() {
+        let mut rows = vec![".x........", "..x.......", "xxx......."];
+        rows.resize(10, "..........");
+        let cycle = first_cycle(&map_of(&rows), 40).unwrap();
+        assert_eq!((cycle.start, cycle.period, cycle.shift), (0, 4, (1, 1)));
+        assert_eq!(cycle.describe(), "Cycle of period 4 since generation 0, moving (1, 1) per period.");
+    }
+
+    #[test]
+    fn still_life_is_only_period_one() {
+        let block = map_of(&["......", ".xx...", ".xx...", "......", "......"]);
+        let mut detector = CycleDetector::new(DEFAULT_WINDOW);
+        assert!(detector.observe(0, &block).is_none());
+        for generation in 1..=DEFAULT_WINDOW as u32 + 10 {
+            let cycle = detector.observe(generation, &block).unwrap();
+            assert_eq!((cycle.start, cycle.period, cycle.shift), (generation - 1, 1, (0, 0)));
+        }
+    }
+
+    #[test]
+    fn same_counts_are_not_a_repeat() {
+        // Both have one cell in every row and column, but neither is a shift of the other.
+        let mut detector = CycleDetector::new(DEFAULT_WINDOW);
+        assert!(detector.observe(0, &map_of(&["x..", ".x.", "..x"])).is_none());
+        assert!(detector.observe(1, &map_of(&["..x", ".x.", "x.."])).is_none());
+        assert!(detector.observe(2, &map_of(&[".x.", "..x", "x.."])).is_some());
+    }
+}
@@ -0,0 +1,204 @@
+// Shape tools for the editor.
+//
+// Every tool returns the list of cells it covers. The caller decides whether
+// those cells become alive or dead and records the edit in the history,
+// just like a single Set/Clear cell.
+
+use crate::{Cell, Coordinates, Vectrix};
+
+// Bresenham's line between two cells, both ends included.
+pub fn line(from: &Coordinates, to: &Coordinates) -> Vec<Coordinates> {
+    let mut points = Vec::<Coordinates>::new();
+
+    let (mut row, mut col) = (from.row as i64, from.col as i64);
+    let (row_end, col_end) = (to.row as i64, to.col as i64);
+
+    let d_col = (col_end - col).abs();
+    let d_row = -(row_end - row).abs();
+    let step_col = if col < col_end { 1 } else { -1 };
+    let step_row = if row < row_end { 1 } else { -1 };
+    let mut error = d_col + d_row;
+
+    loop {
+        points.push(Coordinates::new(row as usize, col as usize));
+        if row == row_end && col == col_end {
+            break;
+        }
+        let error_2 = 2 * error;
+        if error_2 >= d_row {
+            error += d_row;
+            col += step_col;
+        }
+        if error_2 <= d_col {
+            error += d_col;
+            row += step_row;
+        }
+    }
+    points
+}
+
+pub fn rectangle(corner_a: &Coordinates, corner_b: &Coordinates, filled: bool) -> Vec<Coordinates> {
+    shape(corner_a, corner_b, filled, |_, _| true)
+}
+
+// Ellipse inscribed in the rectangle between both corners.
+pub fn ellipse(corner_a: &Coordinates, corner_b: &Coordinates, filled: bool) -> Vec<Coordinates> {
+    let top = corner_a.row.min(corner_b.row) as f64;
+    let bottom = corner_a.row.max(corner_b.row) as f64;
+    let left = corner_a.col.min(corner_b.col) as f64;
+    let right = corner_a.col.max(corner_b.col) as f64;
+
+    let center_row = (top + bottom) / 2.0;
+    let center_col = (left + right) / 2.0;
+    // Half a cell extra so the cells on the bounding box are inside too.
+    let radius_row = (bottom - top) / 2.0 + 0.5;
+    let radius_col = (right - left) / 2.0 + 0.5;
+
+    shape(corner_a, corner_b, filled, |i, j| {
+        let d_row = (i as f64 - center_row) / radius_row;
+        let d_col = (j as f64 - center_col) / radius_col;
+        d_row * d_row + d_col * d_col <= 1.0
+    })
+}
+
+// Cells of the bounding box for which `inside` is true.
+// A hollow shape keeps only the cells that touch the outside.
+fn shape<F>(corner_a: &Coordinates, corner_b: &Coordinates, filled: bool, inside: F) -> Vec<Coordinates>
+where
+    F: Fn(usize, usize) -> bool,
+{
+    let top = corner_a.row.min(corner_b.row);
+    let bottom = corner_a.row.max(corner_b.row);
+    let left = corner_a.col.min(corner_b.col);
+    let right = corner_a.col.max(corner_b.col);
+
+    let is_inside = |i: i64, j: i64| {
+        i >= top as i64
+            && i <= bottom as i64
+            && j >= left as i64
+            && j <= right as i64
+            && inside(i as usize, j as usize)
+    };
+
+    let mut points = Vec::<Coordinates>::new();
+    for i in top..=bottom {
+        for j in left..=right {
+            if !inside(i, j) {
+                continue;
+            }
+            let (i_chk, j_chk) = (i as i64, j as i64);
+            let on_edge = !is_inside(i_chk - 1, j_chk)
+                || !is_inside(i_chk + 1, j_chk)
+                || !is_inside(i_chk, j_chk - 1)
+                || !is_inside(i_chk, j_chk + 1);
+            if filled || on_edge {
+                points.push(Coordinates::new(i, j));
+            }
+        }
+    }
+    points
+}
+
+// Every cell connected to `seed` (up, down, left, right) in the same state.
+// Connections wrap around the edges like the rest of the map.
+pub fn flood_fill(map: &Vectrix, seed: &Coordinates) -> Vec<Coordinates> {
+    let row_len = map.len();
+    let col_len = map[0].len();
+    let seed = Coordinates::new(seed.row % row_len, seed.col % col_len);
+    let seed_alive = matches!(map[seed.row][seed.col], Cell::Alive(..));
+
+    let mut visited = vec![vec![false; col_len]; row_len];
+    let mut points = Vec::<Coordinates>::new();
+    let mut pending = vec![seed];
+
+    while let Some(point) = pending.pop() {
+        if visited[point.row][point.col] {
+            continue;
+        }
+        if matches!(map[point.row][point.col], Cell::Alive(..)) != seed_alive {
+            continue;
+        }
+        visited[point.row][point.col] = true;
+
+        pending.push(Coordinates::new((point.row + row_len - 1) % row_len, point.col));
+        pending.push(Coordinates::new((point.row + 1) % row_len, point.col));
+        pending.push(Coordinates::new(point.row, (point.col + col_len - 1) % col_len));
+        pending.push(Coordinates::new(point.row, (point.col + 1) % col_len));
+        points.push(point);
+    }
+    points
+}
+
+// Set every point to `cell`, wrapping around the edges.
+pub fn apply(map: &mut Vectrix, points: &[Coordinates], cell: &Cell) {
+    let row_len = map.len();
+    let col_len = map[0].len();
+
+    for point in points {
+        map[point.row % row_len][point.col % col_len] = cell.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The points drawn on a map of `rows` x `cols`, x for every point.
+    fn drawn(rows: usize, cols: usize, points: &[Coordinates]) -> Vec<String> {
+        let mut map = vec![vec![Cell::dead(); cols]; rows];
+        apply(&mut map, points, &Cell::alive());
+        map.iter()
+            .map(|row| row.iter().map(|cell| if let Cell::Alive(..) = cell { 'x' } else { '.' }).collect())
+            .collect()
+    }
+
+    #[test]
+    fn lines_include_both_ends() {
+        let points = line(&Coordinates::new(0, 0), &Coordinates::new(2, 4));
+        assert_eq!(drawn(3, 5, &points), ["x....", ".xx..", "...xx"]);
+        // One cell per column on a shallow line, whichever way it goes.
+        let back = line(&Coordinates::new(2, 4), &Coordinates::new(0, 0));
+        assert_eq!(back.len(), 5);
+        assert_eq!((back[0].row, back[0].col, back[4].row, back[4].col), (2, 4, 0, 0));
+        assert_eq!(line(&Coordinates::new(1, 1), &Coordinates::new(1, 1)).len(), 1);
+    }
+
+    #[test]
+    fn rectangles_hollow_and_filled() {
+        let (a, b) = (Coordinates::new(3, 3), Coordinates::new(0, 0));
+        assert_eq!(drawn(4, 4, &rectangle(&a, &b, false)), ["xxxx", "x..x", "x..x", "xxxx"]);
+        assert_eq!(rectangle(&a, &b, true).len(), 16);
+    }
+
+    #[test]
+    fn ellipses_fit_their_box() {
+        let points = ellipse(&Coordinates::new(0, 0), &Coordinates::new(4, 6), true);
+        assert!(points.iter().all(|point| point.row <= 4 && point.col <= 6));
+        let rows = drawn(5, 7, &points);
+        // Mirrored both ways, and round: no corners.
+        for (row, mirror) in rows.iter().zip(rows.iter().rev()) {
+            assert_eq!(row, mirror);
+            assert_eq!(*row, row.chars().rev().collect::<String>());
+        }
+        assert_eq!(rows[2], "xxxxxxx");
+        assert!(rows[0].starts_with('.'));
+
+        let hollow = ellipse(&Coordinates::new(0, 0), &Coordinates::new(4, 6), false);
+        assert_eq!(drawn(5, 7, &hollow)[2], "x.....x");
+    }
+
+    #[test]
+    fn flood_fill_stops_at_other_cells_and_wraps() {
+        let mut map = vec![vec![Cell::dead(); 5]; 3];
+        for row in map.iter_mut() {
+            row[2] = Cell::alive();
+        }
+        // Left and right of the wall are joined across the edge.
+        let points = flood_fill(&map, &Coordinates::new(0, 0));
+        assert_eq!(points.len(), 12);
+        assert!(points.iter().all(|point| point.col != 2));
+
+        let wall = flood_fill(&map, &Coordinates::new(1, 2));
+        assert_eq!(wall.len(), 3);
+    }
+}
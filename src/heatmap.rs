@@ -0,0 +1,223 @@
+// Heat map of cell activity over a game.
+//
+// Every cell counts how many times it changed state and how many generations
+// it was alive. Shown as colours, from black (never) to white (the busiest
+// cell), it makes active regions, oscillator rotors and dead zones easy to
+// spot. It can also be saved as a PPM image, a format simple enough to
+// write by hand that most image viewers open.
+
+use crate::{Cell, Vectrix};
+
+// Pixels per cell in the image.
+const IMAGE_SCALE: usize = 8;
+
+// Colour stops of the gradient, cold to hot.
+const GRADIENT: [(u8, u8, u8); 6] = [
+    (0, 0, 0),
+    (0, 0, 160),
+    (0, 160, 200),
+    (230, 200, 0),
+    (220, 30, 0),
+    (255, 255, 255),
+];
+
+#[derive(Clone, Copy)]
+pub enum HeatMode {
+    // How many times the cell was born or died.
+    Changes,
+    // How many generations the cell was alive.
+    Alive,
+}
+
+impl HeatMode {
+    pub fn name(&self) -> &str {
+        match self {
+            HeatMode::Changes => "Changes",
+            HeatMode::Alive => "Alive",
+        }
+    }
+}
+
+pub struct HeatMap {
+    pub generations: u32,
+    changes: Vec<Vec<u32>>,
+    alive: Vec<Vec<u32>>,
+}
+
+impl HeatMap {
+    pub fn new() -> HeatMap {
+        HeatMap {
+            generations: 0,
+            changes: vec![],
+            alive: vec![],
+        }
+    }
+
+    // Call once per generation. `previous` is None for the first one.
+    pub fn record(&mut self, previous: Option<&Vectrix>, map: &Vectrix) {
+        if self.alive.is_empty() {
+            self.changes = vec![vec![0; map[0].len()]; map.len()];
+            self.alive = vec![vec![0; map[0].len()]; map.len()];
+        }
+
+        for (i, row) in map.iter().enumerate() {
+            for (j, cell) in row.iter().enumerate() {
+                let alive = matches!(cell, Cell::Alive(..));
+                if alive {
+                    self.alive[i][j] += 1;
+                }
+                if let Some(previous) = previous
+                    && alive != matches!(previous[i][j], Cell::Alive(..))
+                {
+                    self.changes[i][j] += 1;
+                }
+            }
+        }
+        self.generations += 1;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.generations == 0
+    }
+
+    fn counts(&self, mode: HeatMode) -> &Vec<Vec<u32>> {
+        match mode {
+            HeatMode::Changes => &self.changes,
+            HeatMode::Alive => &self.alive,
+        }
+    }
+
+    fn highest(&self, mode: HeatMode) -> u32 {
+        self.counts(mode).iter().flatten().copied().max().unwrap_or(0)
+    }
+
+    // Colour of every cell, scaled to the highest count.
+    fn colours(&self, mode: HeatMode) -> Vec<Vec<(u8, u8, u8)>> {
+        let highest = self.highest(mode);
+        self.counts(mode)
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|count| match highest {
+                        0 => GRADIENT[0],
+                        _ => gradient(*count as f64 / highest as f64),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    // Lines for the terminal, two characters per cell, at most `rows` x `cols`
+    // cells. Cells alive in `map` are drawn on top of their colour.
+    pub fn render(&self, map: &Vectrix, mode: HeatMode, rows: usize, cols: usize) -> Vec<String> {
+        let colours = self.colours(mode);
+        let mut lines = Vec::<String>::new();
+
+        for (i, colour_row) in colours.iter().take(rows).enumerate() {
+            let mut line = String::new();
+            for (j, (r, g, b)) in colour_row.iter().take(cols).enumerate() {
+                let text = match map.get(i).and_then(|row| row.get(j)) {
+                    Some(Cell::Alive(..)) => "■ ",
+                    _ => "  ",
+                };
+                line.push_str(&format!("\x1b[48;2;{r};{g};{b}m\x1b[97m{text}"));
+            }
+            line.push_str("\x1b[0m");
+            lines.push(line);
+        }
+        lines
+    }
+
+    // The gradient with the lowest and highest count at its ends.
+    pub fn legend(&self, mode: HeatMode) -> String {
+        let mut legend = String::from("0 ");
+        for step in 0..=20 {
+            let (r, g, b) = gradient(step as f64 / 20.0);
+            legend.push_str(&format!("\x1b[48;2;{r};{g};{b}m "));
+        }
+        legend.push_str(&format!(
+            "\x1b[0m {} ({} over {} generations)",
+            self.highest(mode),
+            mode.name(),
+            self.generations
+        ));
+        legend
+    }
+
+    // Binary PPM (P6), IMAGE_SCALE pixels per cell.
+    pub fn save_image(&self, filename: &str, mode: HeatMode) -> String {
+        if self.is_empty() {
+            return String::from("[-] No heat map yet. Play a game first.");
+        }
+
+        let colours = self.colours(mode);
+        let width = colours[0].len() * IMAGE_SCALE;
+        let height = colours.len() * IMAGE_SCALE;
+        let mut image = format!("P6\n{width} {height}\n255\n").into_bytes();
+
+        for colour_row in &colours {
+            for _ in 0..IMAGE_SCALE {
+                for (r, g, b) in colour_row {
+                    for _ in 0..IMAGE_SCALE {
+                        image.extend_from_slice(&[*r, *g, *b]);
+                    }
+                }
+            }
+        }
+
+        match std::fs::write(filename, image) {
+            Ok(_) => format!("[+] {} heat map saved to {filename} ({width} x {height} pixels).", mode.name()),
+            Err(_) => String::from("[-] Failed to save heat map."),
+        }
+    }
+}
+
+// Colour for `t` from 0 (cold) to 1 (hot), blending the nearest two stops.
+fn gradient(t: f64) -> (u8, u8, u8) {
+    let position = t.clamp(0.0, 1.0) * (GRADIENT.len() - 1) as f64;
+    let low = (position.floor() as usize).min(GRADIENT.len() - 2);
+    let blend = position - low as f64;
+
+    let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * blend).round() as u8;
+    let (from, to) = (GRADIENT[low], GRADIENT[low + 1]);
+    (mix(from.0, to.0), mix(from.1, to.1), mix(from.2, to.2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::next_generation;
+    use crate::rule::Rule;
+
+    #[test]
+    fn blinker_counts() {
+        let mut map: Vectrix = vec![vec![Cell::dead(); 5]; 5];
+        map[2][1..4].fill(Cell::alive());
+        let mut heat_map = HeatMap::new();
+        heat_map.record(None, &map);
+        for _ in 0..3 {
+            let next = next_generation(&map, &Rule::life());
+            heat_map.record(Some(&map), &next);
+            map = next;
+        }
+
+        assert_eq!(heat_map.generations, 4);
+        // The centre never changes, the four tips change every generation.
+        assert_eq!((heat_map.alive[2][2], heat_map.changes[2][2]), (4, 0));
+        assert_eq!((heat_map.alive[2][1], heat_map.changes[2][1]), (2, 3));
+        assert_eq!((heat_map.alive[1][2], heat_map.changes[1][2]), (2, 3));
+        assert_eq!((heat_map.highest(HeatMode::Changes), heat_map.highest(HeatMode::Alive)), (3, 4));
+
+        let colours = heat_map.colours(HeatMode::Changes);
+        assert_eq!((colours[2][1], colours[2][2]), ((255, 255, 255), (0, 0, 0)));
+    }
+
+    #[test]
+    fn gradient_ends_and_middle() {
+        assert_eq!(gradient(0.0), GRADIENT[0]);
+        assert_eq!(gradient(1.0), GRADIENT[5]);
+        assert_eq!(gradient(2.0), GRADIENT[5]);
+        // Halfway between the 3rd and 4th stops.
+        assert_eq!(gradient(0.5), (115, 180, 100));
+    }
+}
@@ -0,0 +1,274 @@
+// Undo/redo history for map edits.
+//
+// Every edit is stored as the list of cells it changed, so toggling one cell
+// on a big map costs one entry and not a copy of the whole map.
+// Edits that change the map size (resize, load) keep both maps, and edits
+// that change the rule keep both rules.
+// When the history gets over its memory budget the oldest edits are dropped.
+
+use std::collections::VecDeque;
+
+use crate::{Cell, Rule, Vectrix};
+
+// 16 MB of history.
+pub const HISTORY_BUDGET: usize = 16 * 1024 * 1024;
+
+enum Edit {
+    // (row, col, before, after)
+    Cells(Vec<(usize, usize, Cell, Cell)>),
+    // (before, after)
+    Replace(Vectrix, Vectrix),
+}
+
+struct Step {
+    label: String,
+    edit: Edit,
+    // (before, after), only when the rule changed.
+    rules: Option<(Rule, Rule)>,
+    bytes: usize,
+}
+
+pub struct History {
+    undo: VecDeque<Step>,
+    redo: Vec<Step>,
+    budget: usize,
+    used: usize,
+}
+
+impl History {
+    pub fn new(budget: usize) -> History {
+        History {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            budget,
+            used: 0,
+        }
+    }
+
+    // Compare the map before and after an edit and remember what changed.
+    // Recording a new edit throws away whatever could be redone.
+    pub fn record(&mut self, label: &str, before: &Vectrix, after: &Vectrix) {
+        self.push(label, before, after, None);
+    }
+
+    // Like `record`, for edits that can change the rule too (load, set rule).
+    // Undo and redo bring the rule back with the cells, so cells never run
+    // under a rule they weren't made for.
+    pub fn record_with_rule(&mut self, label: &str, before: &Vectrix, after: &Vectrix, rule_before: &Rule, rule_after: &Rule) {
+        let rules = match rule_before.name == rule_after.name {
+            true => None,
+            false => Some((rule_before.clone(), rule_after.clone())),
+        };
+        self.push(label, before, after, rules);
+    }
+
+    fn push(&mut self, label: &str, before: &Vectrix, after: &Vectrix, rules: Option<(Rule, Rule)>) {
+        let same_size = before.len() == after.len() && before[0].len() == after[0].len();
+
+        let edit = if same_size {
+            let mut cells = Vec::new();
+            for (i, (row_before, row_after)) in before.iter().zip(after).enumerate() {
+                for (j, (cell_before, cell_after)) in row_before.iter().zip(row_after).enumerate() {
+                    if cell_before != cell_after {
+                        cells.push((i, j, cell_before.clone(), cell_after.clone()));
+                    }
+                }
+            }
+            // Nothing changed, nothing to undo.
+            if cells.is_empty() && rules.is_none() {
+                return;
+            }
+            Edit::Cells(cells)
+        } else {
+            Edit::Replace(before.clone(), after.clone())
+        };
+
+        for step in self.redo.drain(..) {
+            self.used -= step.bytes;
+        }
+
+        let bytes = edit_size(&edit);
+        self.used += bytes;
+        self.undo.push_back(Step {
+            label: label.to_string(),
+            edit,
+            rules,
+            bytes,
+        });
+
+        // Forget the oldest edits until we are back under budget.
+        while self.used > self.budget {
+            match self.undo.pop_front() {
+                Some(step) => self.used -= step.bytes,
+                None => break,
+            }
+        }
+    }
+
+    pub fn undo(&mut self, map: &mut Vectrix, rule: &mut Rule) -> String {
+        let step = match self.undo.pop_back() {
+            Some(step) => step,
+            None => return String::from("[-] Nothing to undo."),
+        };
+
+        match &step.edit {
+            Edit::Cells(cells) => {
+                for (i, j, before, _) in cells {
+                    map[*i][*j] = before.clone();
+                }
+            },
+            Edit::Replace(before, _) => *map = before.clone(),
+        }
+        if let Some((before, _)) = &step.rules {
+            *rule = before.clone();
+        }
+
+        let message = format!("[+] Undo: {}", step.label);
+        self.redo.push(step);
+        message
+    }
+
+    pub fn redo(&mut self, map: &mut Vectrix, rule: &mut Rule) -> String {
+        let step = match self.redo.pop() {
+            Some(step) => step,
+            None => return String::from("[-] Nothing to redo."),
+        };
+
+        match &step.edit {
+            Edit::Cells(cells) => {
+                for (i, j, _, after) in cells {
+                    map[*i][*j] = after.clone();
+                }
+            },
+            Edit::Replace(_, after) => *map = after.clone(),
+        }
+        if let Some((_, after)) = &step.rules {
+            *rule = after.clone();
+        }
+
+        let message = format!("[+] Redo: {}", step.label);
+        self.undo.push_back(step);
+        message
+    }
+}
+
+// Rough number of bytes an edit keeps alive.
+fn edit_size(edit: &Edit) -> usize {
+    let cell_size = std::mem::size_of::<Cell>() + 4;
+    match edit {
+        Edit::Cells(cells) => cells.len() * (2 * std::mem::size_of::<usize>() + 2 * cell_size),
+        Edit::Replace(before, after) => {
+            (before.len() * before[0].len() + after.len() * after[0].len()) * cell_size
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(rows: usize, cols: usize) -> Vectrix {
+        vec![vec![Cell::dead(); cols]; rows]
+    }
+
+    #[test]
+    fn undo_and_redo_a_cell() {
+        let mut history = History::new(HISTORY_BUDGET);
+        let mut rule = Rule::life();
+        let mut grid = map(3, 3);
+        let before = grid.clone();
+        grid[1][2] = Cell::alive();
+        history.record("Set cell", &before, &grid);
+        let after = grid.clone();
+
+        assert_eq!(history.undo(&mut grid, &mut rule), "[+] Undo: Set cell");
+        assert!(grid == before);
+        assert_eq!(history.redo(&mut grid, &mut rule), "[+] Redo: Set cell");
+        assert!(grid == after);
+        assert_eq!(history.redo(&mut grid, &mut rule), "[-] Nothing to redo.");
+    }
+
+    #[test]
+    fn new_edits_forget_the_redo() {
+        let mut history = History::new(HISTORY_BUDGET);
+        let mut rule = Rule::life();
+        let mut grid = map(2, 2);
+        let before = grid.clone();
+        grid[0][0] = Cell::alive();
+        history.record("First", &before, &grid);
+        history.undo(&mut grid, &mut rule);
+
+        let before = grid.clone();
+        grid[1][1] = Cell::alive();
+        history.record("Second", &before, &grid);
+        assert_eq!(history.redo(&mut grid, &mut rule), "[-] Nothing to redo.");
+
+        // Nothing changed, so nothing is recorded.
+        history.record("Nothing", &grid.clone(), &grid);
+        assert_eq!(history.undo(&mut grid, &mut rule), "[+] Undo: Second");
+        assert_eq!(history.undo(&mut grid, &mut rule), "[-] Nothing to undo.");
+    }
+
+    #[test]
+    fn resizing_keeps_both_maps() {
+        let mut history = History::new(HISTORY_BUDGET);
+        let mut rule = Rule::life();
+        let small = map(2, 2);
+        let mut grid = map(4, 5);
+        history.record("Resize map", &small, &grid);
+
+        history.undo(&mut grid, &mut rule);
+        assert_eq!((grid.len(), grid[0].len()), (2, 2));
+        history.redo(&mut grid, &mut rule);
+        assert_eq!((grid.len(), grid[0].len()), (4, 5));
+    }
+
+    #[test]
+    fn undo_brings_the_rule_back() {
+        let mut history = History::new(HISTORY_BUDGET);
+        let life = Rule::life();
+        let mut rule = Rule::parse("B2/S/C3").unwrap();
+        let mut grid = map(3, 3);
+        let before = grid.clone();
+        grid[1][1] = Cell::dying(2);
+        history.record_with_rule("Load map", &before, &grid, &life, &rule);
+
+        // The dying cell goes with the rule that has dying cells.
+        history.undo(&mut grid, &mut rule);
+        assert!(grid == before);
+        assert_eq!(rule.name, life.name);
+        history.redo(&mut grid, &mut rule);
+        assert_eq!(rule.states, 3);
+        assert_eq!(grid[1][1].state(), 2);
+
+        // Only the rule changed.
+        let before_rule = rule.clone();
+        rule = Rule::parse("B36/S23").unwrap();
+        history.record_with_rule("Set rule", &grid.clone(), &grid, &before_rule, &rule);
+        assert_eq!(history.undo(&mut grid, &mut rule), "[+] Undo: Set rule");
+        assert_eq!(rule.name, before_rule.name);
+    }
+
+    #[test]
+    fn oldest_edits_go_over_budget() {
+        let mut grid = map(10, 10);
+        // Room for a bit more than one edit of 10 cells.
+        let one_edit = {
+            let mut edits = History::new(HISTORY_BUDGET);
+            let mut after = grid.clone();
+            after[0] = vec![Cell::alive(); 10];
+            edits.record("Row", &grid, &after);
+            edits.used
+        };
+        let mut history = History::new(one_edit * 3 / 2);
+        let mut rule = Rule::life();
+
+        for row in 0..3 {
+            let before = grid.clone();
+            grid[row] = vec![Cell::alive(); 10];
+            history.record(&format!("Row {row}"), &before, &grid);
+        }
+        assert_eq!(history.undo(&mut grid, &mut rule), "[+] Undo: Row 2");
+        assert_eq!(history.undo(&mut grid, &mut rule), "[-] Nothing to undo.");
+    }
+}
@@ -0,0 +1,128 @@
+// Neighbour counting for Larger than Life rules.
+//
+// With a radius of 10 a cell has up to 440 neighbours, too many to visit one
+// by one for every cell. Instead a summed-area table is built once per
+// generation: each entry holds the live cells above and to the left of it, so
+// the live cells of any rectangle take 4 lookups. A box neighbourhood is one
+// rectangle; diamonds and circles are one rectangle per row.
+
+use crate::rule::RangeShape;
+use crate::{Cell, Vectrix};
+
+// Neighbours of every cell of the map.
+pub fn counts(map: &Vectrix, radius: u32, shape: RangeShape, middle: bool) -> Vec<Vec<u32>> {
+    let rows = map.len();
+    let cols = map[0].len();
+    let r = radius as usize;
+
+    // The map with a border of `r` cells from the other side (it's a torus),
+    // so every neighbourhood fits inside.
+    let padded_rows = rows + 2 * r;
+    let padded_cols = cols + 2 * r;
+    let wrap = |index: usize, size: usize| (index as i64 - r as i64).rem_euclid(size as i64) as usize;
+
+    // table[i][j] = live cells in padded rows < i and cols < j.
+    let mut table = vec![vec![0u32; padded_cols + 1]; padded_rows + 1];
+    for i in 0..padded_rows {
+        let map_row = &map[wrap(i, rows)];
+        for j in 0..padded_cols {
+            let alive = matches!(map_row[wrap(j, cols)], Cell::Alive(..)) as u32;
+            table[i + 1][j + 1] = alive + table[i][j + 1] + table[i + 1][j] - table[i][j];
+        }
+    }
+    // Live cells from (top, left) to (bottom, right), both included.
+    let rectangle = |top: usize, left: usize, bottom: usize, right: usize| {
+        table[bottom + 1][right + 1] + table[top][left] - table[top][right + 1] - table[bottom + 1][left]
+    };
+
+    // Half width of each row of the neighbourhood, top to bottom.
+    let widths: Vec<usize> = (0..=2 * r)
+        .map(|row| {
+            let d_row = row.abs_diff(r);
+            match shape {
+                RangeShape::Moore => r,
+                RangeShape::VonNeumann => r - d_row,
+                RangeShape::Circular => ((r * r + r - d_row * d_row) as f64).sqrt() as usize,
+            }
+        })
+        .collect();
+
+    let mut counts = vec![vec![0u32; cols]; rows];
+    for i in 0..rows {
+        for j in 0..cols {
+            // (i, j) on the map is (i + r, j + r) on the padded map.
+            let mut count = match shape {
+                RangeShape::Moore => rectangle(i, j, i + 2 * r, j + 2 * r),
+                _ => widths
+                    .iter()
+                    .enumerate()
+                    .map(|(row, width)| rectangle(i + row, j + r - width, i + row, j + r + width))
+                    .sum(),
+            };
+            if !middle && matches!(map[i][j], Cell::Alive(..)) {
+                count -= 1;
+            }
+            counts[i][j] = count;
+        }
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rule::Neighbourhood;
+
+    // Counts every neighbour one by one, wrapping around the edges.
+    fn brute_force(map: &Vectrix, neighbourhood: &Neighbourhood) -> Vec<Vec<u32>> {
+        let rows = map.len() as i64;
+        let cols = map[0].len() as i64;
+        let offsets = neighbourhood.offsets(false);
+
+        (0..rows)
+            .map(|i| {
+                (0..cols)
+                    .map(|j| {
+                        offsets
+                            .iter()
+                            .filter(|(d_row, d_col, _)| {
+                                let cell = &map[(i + d_row).rem_euclid(rows) as usize][(j + d_col).rem_euclid(cols) as usize];
+                                matches!(cell, Cell::Alive(..))
+                            })
+                            .count() as u32
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn summed_area_matches_brute_force() {
+        // Scattered cells, some of them on the edges so wrapping matters.
+        let map: Vectrix = (0..9)
+            .map(|i| (0..11).map(|j| if (i * 7 + j * 3 + i * j) % 5 == 0 { Cell::alive() } else { Cell::dead() }).collect())
+            .collect();
+
+        // A radius of 6 is wider than the map, so the neighbourhood wraps onto itself.
+        for radius in [1, 2, 3, 6] {
+            for shape in [RangeShape::Moore, RangeShape::VonNeumann, RangeShape::Circular] {
+                for middle in [false, true] {
+                    let neighbourhood = Neighbourhood::Range { radius, shape, middle };
+                    assert!(
+                        counts(&map, radius, shape, middle) == brute_force(&map, &neighbourhood),
+                        "radius {radius}, middle {middle}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn middle_counts_the_cell_itself() {
+        let mut map: Vectrix = vec![vec![Cell::dead(); 5]; 5];
+        map[2][2] = Cell::alive();
+        assert_eq!(counts(&map, 1, RangeShape::Moore, false)[2][2], 0);
+        assert_eq!(counts(&map, 1, RangeShape::Moore, true)[2][2], 1);
+        assert_eq!(counts(&map, 1, RangeShape::Moore, false)[1][1], 1);
+    }
+}
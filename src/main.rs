@@ -26,9 +26,51 @@
 // [ ] Command Line Argument Parser, to get map dimensions.
 // [x] Filesystem functions, to Load and Save maps.
 
+mod census;
+mod clipboard;
+mod cycle;
+mod draw;
+mod heatmap;
+mod history;
+mod ltl;
+mod net;
+mod pattern;
+mod plane;
+mod rule;
+mod soup;
+mod spectate;
+mod speed;
+mod stats;
+mod table;
+mod versus;
+
+use census::Census;
+use clipboard::{Clipboard, PasteMode, Selection};
+use cycle::{Cycle, CycleDetector};
+use heatmap::HeatMode;
+use history::{History, HISTORY_BUDGET};
+use net::{Lockstep, Message, Session, SessionEvent};
+use pattern::{Category, Orientation, Pattern};
+use plane::Plane;
+use rule::{Neighbourhood, RING, Rule};
+use table::{Lookup, RuleTable};
+use soup::{Rng, Soup, Symmetry};
+use spectate::Spectators;
+use speed::Velocity;
+use stats::{Statistics, PANEL_COLS};
+use versus::Versus;
+
 type Vectrix = Vec<Vec<Cell>>;
 
 fn main() {
+    // Check the pattern library and exit, e.g. `cargo run --release -- --verify-patterns`
+    if std::env::args().any(|arg| arg == "--verify-patterns") {
+        match pattern::verify_catalog() {
+            true => std::process::exit(0),
+            false => std::process::exit(1),
+        }
+    }
+
     clear_console();
     let mut message: String;
     message = String::from("Welcome.");
@@ -44,10 +86,34 @@ fn main() {
         infinite_game: true,
         max_generations: 50,
         map_size: (10, 10),
+        stop_when_stable: true,
+        stop_on_cycle: true,
+        cycle_window: cycle::DEFAULT_WINDOW,
+        show_statistics: true,
+        rule: Rule::life(),
     };
 
+    // Statistics of the last game played
+    let mut statistics = Statistics::new();
+
+    // Undo/redo of map edits
+    let mut history = History::new(HISTORY_BUDGET);
+
+    // Selected region and copied cells
+    let mut selection: Option<Selection> = None;
+    let mut clipboard: Option<Clipboard> = None;
+    let mut paste_mode = PasteMode::Or;
+
+    // Computers watching this one play, see spectate.rs.
+    let mut spectators: Option<Spectators> = None;
+
     // Menu loop
     loop {
+        // Spectators see the map change outside of games too.
+        if let Some(spectators) = &mut spectators {
+            spectators.serve(&map, &game_properties.rule);
+        }
+
         clear_console();
         print_header(vec!["Game of Life"]);
         print_map(&map, true, true);
@@ -57,21 +123,43 @@ fn main() {
 
         match menu_opt {
             MainMenuOpt::SetClearCell => {
-                message = Map::set_clear_cell(&mut map)
+                message = Map::set_clear_cell(&mut map, &mut history, &mut game_properties.rule)
             },
             MainMenuOpt::GeneratePattern => {
-                // Option in development. Need to give ability of choosing pattern.
-                message = String::from("Generate pattern. Set origin.");
+                let pattern = match choose_pattern() {
+                    Some(chosen) => chosen,
+                    None => {
+                        message = String::from("Pattern generation aborted.");
+                        continue;
+                    },
+                };
+                let orientation = match choose_orientation(&pattern) {
+                    Some(orientation) => orientation,
+                    None => {
+                        message = String::from("Pattern generation aborted.");
+                        continue;
+                    },
+                };
+
+                message = format!("Generate {}. Set origin.", pattern.name);
                 loop {
                     clear_console();
-                    print_header(vec!["Fame of Light"]);
+                    print_header(vec!["Game of Life"]);
                     print_map(&map, true, true);
                     print_message(&message, true);
 
-                    let (row, aborted) = get_usize(&String::from("Row"), true);
-                    if aborted {
-                        break;
-                    }
+                    let row = match get_edit_usize("Row") {
+                        EditInput::Value(row) => row,
+                        EditInput::Undo => {
+                            message = history.undo(&mut map, &mut game_properties.rule);
+                            continue;
+                        },
+                        EditInput::Redo => {
+                            message = history.redo(&mut map, &mut game_properties.rule);
+                            continue;
+                        },
+                        EditInput::Quit => break,
+                    };
                     
                     let (col, aborted) = get_usize(&String::from("Col"), true);
                     if aborted {
@@ -80,29 +168,34 @@ fn main() {
 
                     let origin = Coordinates::new(row, col);
 
-                    let glider = Spaceship::glider(origin.clone());
-
-                    let fifteen_bent_paperclip = StillLife::fifteen_bent_paperclip(origin);
-
-                    let glider_points = Spaceship::get_points(glider);
-
-                    let fift_paperclip_points = StillLife::get_points(fifteen_bent_paperclip);
-
-                    // message = Map::generate_spaceship(&mut map, glider);
-                    // message = Map::generate_still_life(&mut map, fifteen_bent_paperclip);
-                    message = Map::generate_pattern(&mut map, glider_points);
+                    let before = map.clone();
+                    Map::generate_pattern(&mut map, pattern.points_at(&origin, &orientation));
+                    history.record("Generate pattern", &before, &map);
+                    message = format!("[+] {} created.", pattern.name);
 
                 }
                 message = String::from("[+] Pattern generation finished.");
             },
             MainMenuOpt::Play => {
-                message = play(&mut map, &game_properties);
+                // Playing is recorded too, so undo can go back to before the game.
+                let before = map.clone();
+                (statistics, message) = play(&mut map, &game_properties, &mut spectators);
+                history.record("Play", &before, &map);
             },
             MainMenuOpt::SaveMap => {
-                message = save_map("map.txt", &map)
+                message = save_map("map.txt", &map, &game_properties.rule)
             },
             MainMenuOpt::LoadMap => {
-                (map, message) = load_map("map.txt")
+                let before = map.clone();
+                let rule_before = game_properties.rule.clone();
+                let rule: Option<Rule>;
+                (map, rule, message) = load_map("map.txt");
+                // Maps without a `#R` line are Life maps. None if the map
+                // couldn't be loaded.
+                if let Some(rule) = rule {
+                    game_properties.rule = rule;
+                }
+                history.record_with_rule("Load map", &before, &map, &rule_before, &game_properties.rule);
             },
             MainMenuOpt::Configuration => {
                 message = String::from("Game configuration");
@@ -125,12 +218,171 @@ fn main() {
                         ConfigMenuOpt::SetMaxGenerations => {
                             (game_properties.max_generations, message) = set_generations();
                         },
-                        ConfigMenuOpt::SetMapSize => (map, message) = Map::new(),
+                        ConfigMenuOpt::SetMapSize => {
+                            let before = map.clone();
+                            (map, message) = Map::new();
+                            // Keep the old map if the rule can't run on the new one.
+                            if let Err(error) = game_properties.rule.fits(map.len()) {
+                                map = before;
+                                message = format!("[-] Map not resized: {error}.");
+                            } else {
+                                history.record("Resize map", &before, &map);
+                            }
+                        },
+                        ConfigMenuOpt::StopWhenStable => {
+                            (game_properties.stop_when_stable, message) = set_stop_when_stable(&game_properties.stop_when_stable);
+                        },
+                        ConfigMenuOpt::StopOnCycle => {
+                            (game_properties.stop_on_cycle, message) = set_stop_on_cycle(&game_properties.stop_on_cycle);
+                        },
+                        ConfigMenuOpt::SetCycleWindow => {
+                            (game_properties.cycle_window, message) = set_cycle_window();
+                        },
+                        ConfigMenuOpt::ShowStatistics => {
+                            (game_properties.show_statistics, message) = set_show_statistics(&game_properties.show_statistics);
+                        },
+                        ConfigMenuOpt::Spectators => {
+                            message = set_spectators(&mut spectators);
+                        },
+                        ConfigMenuOpt::SetRule => {
+                            let rule_before = game_properties.rule.clone();
+                            (game_properties.rule, message) = set_rule(&game_properties.rule, map.len());
+
+                            let before = map.clone();
+                            fit_to_rule(&mut map, &game_properties.rule);
+                            history.record_with_rule("Set rule", &before, &map, &rule_before, &game_properties.rule);
+                        },
                         ConfigMenuOpt::Exit => break,
                         ConfigMenuOpt::Unknown => (),
                     }
                 }
             },
+            MainMenuOpt::ClearMap => {
+                let before = map.clone();
+                message = Map::clear(&mut map);
+                history.record("Clear map", &before, &map);
+            },
+            MainMenuOpt::Selection => {
+                message = String::from("Select a region to copy, cut, transform and paste.");
+                loop {
+                    clear_console();
+                    print_header(vec!["Game of Life"]);
+                    print_map(&map, true, true);
+                    print_message(&message, true);
+                    print_selection_status(&selection, &clipboard, &paste_mode);
+
+                    let menu_opt = selection_menu();
+
+                    match menu_opt {
+                        SelectionMenuOpt::Select => {
+                            (selection, message) = select_region(&map);
+                        },
+                        SelectionMenuOpt::Copy | SelectionMenuOpt::Cut => {
+                            let region = match &selection {
+                                Some(region) => region,
+                                None => {
+                                    message = String::from("[-] Select a region first.");
+                                    continue;
+                                },
+                            };
+                            if let SelectionMenuOpt::Cut = menu_opt {
+                                let before = map.clone();
+                                clipboard = Some(Clipboard::cut(&mut map, region));
+                                history.record("Cut", &before, &map);
+                                message = format!("[+] Cut {}.", region.describe());
+                            } else {
+                                clipboard = Some(Clipboard::copy(&map, region));
+                                message = format!("[+] Copied {}.", region.describe());
+                            }
+                        },
+                        SelectionMenuOpt::Paste => {
+                            message = match &clipboard {
+                                Some(copied) => paste_clipboard(&mut map, &mut history, copied, paste_mode),
+                                None => String::from("[-] Clipboard is empty."),
+                            };
+                        },
+                        SelectionMenuOpt::Rotate | SelectionMenuOpt::FlipHorizontal | SelectionMenuOpt::FlipVertical => {
+                            let copied = match &mut clipboard {
+                                Some(copied) => copied,
+                                None => {
+                                    message = String::from("[-] Clipboard is empty.");
+                                    continue;
+                                },
+                            };
+                            message = match menu_opt {
+                                SelectionMenuOpt::Rotate => {
+                                    copied.rotate();
+                                    String::from("[+] Clipboard rotated 90 degrees.")
+                                },
+                                SelectionMenuOpt::FlipHorizontal => {
+                                    copied.flip_horizontal();
+                                    String::from("[+] Clipboard flipped horizontally.")
+                                },
+                                _ => {
+                                    copied.flip_vertical();
+                                    String::from("[+] Clipboard flipped vertically.")
+                                },
+                            };
+                        },
+                        SelectionMenuOpt::PasteMode => {
+                            paste_mode = paste_mode.next();
+                            message = format!("Paste mode = {}", paste_mode.name());
+                        },
+                        SelectionMenuOpt::SavePattern => {
+                            message = match &selection {
+                                Some(region) => save_user_pattern(&map, region),
+                                None => String::from("[-] Select a region first."),
+                            };
+                        },
+                        SelectionMenuOpt::MeasureSpeed => {
+                            message = measure_speed(&map, selection.as_ref(), &game_properties.rule);
+                        },
+                        SelectionMenuOpt::Exit => break,
+                        SelectionMenuOpt::Unknown => (),
+                    }
+                }
+                message = String::from("Selection finished.");
+            },
+            MainMenuOpt::Draw => {
+                message = Map::draw(&mut map, &mut history, &mut game_properties.rule);
+            },
+            MainMenuOpt::RandomFill => {
+                message = match random_fill_settings(&map, &game_properties.rule) {
+                    Some((soup, region)) => {
+                        let before = map.clone();
+                        let message = soup.fill(&mut map, &region);
+                        history.record("Random fill", &before, &map);
+                        message
+                    },
+                    None => String::from("Random fill aborted."),
+                };
+            },
+            MainMenuOpt::Census => {
+                // The catalog is all Life patterns, other rules have nothing to match.
+                message = match game_properties.rule.is_life() {
+                    true => Census::take(&map).describe(),
+                    false => format!("[-] The census only knows Life objects, not {} ones.", game_properties.rule.name),
+                };
+            },
+            MainMenuOpt::SaveStatistics => {
+                message = statistics.save_csv("statistics.csv");
+            },
+            MainMenuOpt::HeatMap => {
+                message = show_heat_map(&map, &statistics);
+            },
+            MainMenuOpt::TwoPlayer => {
+                let before = map.clone();
+                message = two_player_game(&mut map, &game_properties);
+                history.record("Two-player game", &before, &map);
+            },
+            MainMenuOpt::Network => {
+                let before = map.clone();
+                let rule_before = game_properties.rule.clone();
+                message = network_session(&mut map, &mut game_properties);
+                history.record_with_rule("Network session", &before, &map, &rule_before, &game_properties.rule);
+            },
+            MainMenuOpt::Undo => message = history.undo(&mut map, &mut game_properties.rule),
+            MainMenuOpt::Redo => message = history.redo(&mut map, &mut game_properties.rule),
             MainMenuOpt::Exit => break,
             MainMenuOpt::Unknown => (),
         }
@@ -145,6 +397,17 @@ enum MainMenuOpt {
     SaveMap,
     LoadMap,
     Configuration,
+    ClearMap,
+    Selection,
+    Draw,
+    RandomFill,
+    Census,
+    SaveStatistics,
+    HeatMap,
+    TwoPlayer,
+    Network,
+    Undo,
+    Redo,
     Exit,
     // Credits,
     Unknown,
@@ -152,27 +415,50 @@ enum MainMenuOpt {
 
 fn main_menu() -> MainMenuOpt {
     let menu_text: String = format!(
-        "{} | {} | {} | {} | {} | {} | {}\n", 
+        "{} | {} | {} | {} | {} | {} | {} | {} | {} | {} | {} | {} | {} | {} | {} | {} | {} | {}\n", 
         "1. Set/Clear cell",
         "2. Generate pattern", 
         "3. Play", 
         "4. Save map", 
         "5. Load map", 
         "6. Configuration", 
+        "7. Clear map",
+        "8. Selection",
+        "9. Draw",
+        "10. Random fill",
+        "11. Census",
+        "12. Save statistics",
+        "13. Heat map",
+        "14. Two-player game",
+        "15. Network session",
+        "u. Undo",
+        "r. Redo",
         "99. Exit",
     );
     print_message(&menu_text, true);
     
-    let opt = get_u32(&String::from("Option: "));
+    // Not get_u32() because of the `u` and `r` shortcuts.
+    let opt = get_input(&String::from("Option: "));
     
-    match opt {
-        1 => MainMenuOpt::SetClearCell,
-        2 => MainMenuOpt::GeneratePattern,
-        3 => MainMenuOpt::Play,
-        4 => MainMenuOpt::SaveMap,
-        5 => MainMenuOpt::LoadMap,
-        6 => MainMenuOpt::Configuration,
-        99 => MainMenuOpt::Exit,
+    match opt.trim() {
+        "1" => MainMenuOpt::SetClearCell,
+        "2" => MainMenuOpt::GeneratePattern,
+        "3" => MainMenuOpt::Play,
+        "4" => MainMenuOpt::SaveMap,
+        "5" => MainMenuOpt::LoadMap,
+        "6" => MainMenuOpt::Configuration,
+        "7" => MainMenuOpt::ClearMap,
+        "8" => MainMenuOpt::Selection,
+        "9" => MainMenuOpt::Draw,
+        "10" => MainMenuOpt::RandomFill,
+        "11" => MainMenuOpt::Census,
+        "12" => MainMenuOpt::SaveStatistics,
+        "13" => MainMenuOpt::HeatMap,
+        "14" => MainMenuOpt::TwoPlayer,
+        "15" => MainMenuOpt::Network,
+        "u" => MainMenuOpt::Undo,
+        "r" => MainMenuOpt::Redo,
+        "99" => MainMenuOpt::Exit,
         _ => MainMenuOpt::Unknown, 
     }
 }
@@ -181,22 +467,38 @@ struct GameConfig {
     tick_rate: u32,
     infinite_game: bool,
     max_generations: u32,
+    #[allow(dead_code)]
     map_size: (u32, u32),
+    // End the game when the universe dies or stops changing.
+    stop_when_stable: bool,
+    // End the game when the universe repeats itself.
+    stop_on_cycle: bool,
+    // How many generations back to look for a repeat.
+    cycle_window: usize,
+    // Statistics panel next to the map while playing.
+    show_statistics: bool,
+    rule: Rule,
 }
 
 // This function is under development
+#[allow(dead_code)]
 impl GameConfig {
     fn get_config() -> GameConfig {
         use std::fs::read_to_string;
 
         let filename = "config.ini";
-        let config = match read_to_string(filename) {
+        let _config = match read_to_string(filename) {
             Ok(data) => data,
             Err(_) => return GameConfig {
                 tick_rate: 75,
                 infinite_game: true,
                 max_generations: 9999,
                 map_size: (10, 10),
+                stop_when_stable: true,
+                stop_on_cycle: true,
+                cycle_window: cycle::DEFAULT_WINDOW,
+                show_statistics: true,
+                rule: Rule::life(),
             },
         };
         GameConfig {
@@ -204,6 +506,11 @@ impl GameConfig {
             infinite_game: true,
             max_generations: 9999,
             map_size: (10, 10),
+            stop_when_stable: true,
+            stop_on_cycle: true,
+            cycle_window: cycle::DEFAULT_WINDOW,
+            show_statistics: true,
+            rule: Rule::life(),
         }
     }
 }
@@ -213,17 +520,29 @@ enum ConfigMenuOpt {
     InfiniteGame,
     SetMaxGenerations,
     SetMapSize,
+    StopWhenStable,
+    StopOnCycle,
+    SetCycleWindow,
+    ShowStatistics,
+    SetRule,
+    Spectators,
     Exit,
     Unknown,
 }
 
 fn config_menu() -> ConfigMenuOpt {
     let config_text: String = format!(
-        "{} | {} | {} | {} | {}\n",
+        "{} | {} | {} | {} | {} | {} | {} | {} | {} | {} | {}\n",
         "1. Set Tick Rate",
         "2. Infinite game",
         "3. Set Max Generations",
         "4. Set Map Size",
+        "5. Stop when stable",
+        "6. Stop on cycle",
+        "7. Set Cycle Window",
+        "8. Statistics panel",
+        "9. Set Rule",
+        "10. Spectators",
         "99. Exit",
     );
     print_message(&config_text, true);
@@ -235,36 +554,1110 @@ fn config_menu() -> ConfigMenuOpt {
         2 => ConfigMenuOpt::InfiniteGame,
         3 => ConfigMenuOpt::SetMaxGenerations,
         4 => ConfigMenuOpt::SetMapSize,
+        5 => ConfigMenuOpt::StopWhenStable,
+        6 => ConfigMenuOpt::StopOnCycle,
+        7 => ConfigMenuOpt::SetCycleWindow,
+        8 => ConfigMenuOpt::ShowStatistics,
+        9 => ConfigMenuOpt::SetRule,
+        10 => ConfigMenuOpt::Spectators,
         99 => ConfigMenuOpt::Exit,
         _ => ConfigMenuOpt::Unknown,
     }
 }
 
-#[derive(Clone)]
+enum SelectionMenuOpt {
+    Select,
+    Copy,
+    Cut,
+    Paste,
+    Rotate,
+    FlipHorizontal,
+    FlipVertical,
+    PasteMode,
+    SavePattern,
+    MeasureSpeed,
+    Exit,
+    Unknown,
+}
+
+fn selection_menu() -> SelectionMenuOpt {
+    let selection_text: String = format!(
+        "{} | {} | {} | {} | {} | {} | {} | {} | {} | {} | {}\n",
+        "1. Select region",
+        "2. Copy",
+        "3. Cut",
+        "4. Paste",
+        "5. Rotate 90",
+        "6. Flip horizontal",
+        "7. Flip vertical",
+        "8. Paste mode",
+        "9. Save as pattern",
+        "10. Measure speed",
+        "99. Exit",
+    );
+    print_message(&selection_text, true);
+
+    let opt = get_u32(&String::from("Option: "));
+
+    match opt {
+        1 => SelectionMenuOpt::Select,
+        2 => SelectionMenuOpt::Copy,
+        3 => SelectionMenuOpt::Cut,
+        4 => SelectionMenuOpt::Paste,
+        5 => SelectionMenuOpt::Rotate,
+        6 => SelectionMenuOpt::FlipHorizontal,
+        7 => SelectionMenuOpt::FlipVertical,
+        8 => SelectionMenuOpt::PasteMode,
+        9 => SelectionMenuOpt::SavePattern,
+        10 => SelectionMenuOpt::MeasureSpeed,
+        99 => SelectionMenuOpt::Exit,
+        _ => SelectionMenuOpt::Unknown,
+    }
+}
+
+fn print_selection_status(selection: &Option<Selection>, clipboard: &Option<Clipboard>, paste_mode: &PasteMode) {
+    let selected = match selection {
+        Some(region) => region.describe(),
+        None => String::from("nothing"),
+    };
+    let copied = match clipboard {
+        Some(copied) => format!("{} x {}, {} cells", copied.rows, copied.cols, copied.points.len()),
+        None => String::from("empty"),
+    };
+    print_message(&format!("Selected: {selected} | Clipboard: {copied} | Paste mode: {}", paste_mode.name()), true);
+}
+
+enum HeatMapMenuOpt {
+    Changes,
+    Alive,
+    SaveImage,
+    Exit,
+    Unknown,
+}
+
+fn heat_map_menu() -> HeatMapMenuOpt {
+    let heat_map_text: String = format!(
+        "{} | {} | {} | {}\n",
+        "1. Changes",
+        "2. Alive",
+        "3. Save image",
+        "99. Exit",
+    );
+    print_message(&heat_map_text, true);
+
+    let opt = get_u32(&String::from("Option: "));
+
+    match opt {
+        1 => HeatMapMenuOpt::Changes,
+        2 => HeatMapMenuOpt::Alive,
+        3 => HeatMapMenuOpt::SaveImage,
+        99 => HeatMapMenuOpt::Exit,
+        _ => HeatMapMenuOpt::Unknown,
+    }
+}
+
+// Heat map of the last game, with the current map on top.
+fn show_heat_map(map: &Vectrix, statistics: &Statistics) -> String {
+    let heat_map = &statistics.heat_map;
+    if heat_map.is_empty() {
+        return String::from("[-] No heat map yet. Play a game first.");
+    }
+
+    let mut mode = HeatMode::Changes;
+    let mut message = String::from("Heat map of the last game");
+    loop {
+        clear_console();
+        print_header(vec!["Game of Life"]);
+
+        // Two characters per cell.
+        let (term_cols, term_rows) = terminal_size();
+        let rows = term_rows.saturating_sub(MENU_LINES).max(1);
+        let cols = (term_cols / 2).max(1);
+        for line in heat_map.render(map, mode, rows, cols) {
+            println!("{line}");
+        }
+        if rows < map.len() || cols < map[0].len() {
+            println!("Showing rows 0-{}, cols 0-{}", rows.min(map.len()) - 1, cols.min(map[0].len()) - 1);
+        }
+        print_message(&heat_map.legend(mode), true);
+        print_message(&message, true);
+
+        match heat_map_menu() {
+            HeatMapMenuOpt::Changes => {
+                mode = HeatMode::Changes;
+                message = String::from("Heat map: how often each cell changed");
+            },
+            HeatMapMenuOpt::Alive => {
+                mode = HeatMode::Alive;
+                message = String::from("Heat map: how long each cell was alive");
+            },
+            HeatMapMenuOpt::SaveImage => {
+                message = heat_map.save_image("heatmap.ppm", mode);
+            },
+            HeatMapMenuOpt::Exit => break,
+            HeatMapMenuOpt::Unknown => (),
+        }
+    }
+    String::from("Heat map closed.")
+}
+
+// Red and Blue place cells in turns and the universe runs between rounds,
+// see versus.rs. The map is cleared first.
+fn two_player_game(map: &mut Vectrix, game_properties: &GameConfig) -> String {
+    let rule = versus_rule(&game_properties.rule);
+    if let Err(error) = rule.fits(map.len()) {
+        return format!("[-] Can't play: {error}.");
+    }
+
+    let (rounds, aborted) = get_usize("Rounds", true);
+    if aborted {
+        return String::from("Aborted");
+    }
+    let (cells_per_turn, aborted) = get_usize("Cells per turn", true);
+    if aborted {
+        return String::from("Aborted");
+    }
+    let (generations_per_round, aborted) = get_usize("Generations per round", true);
+    if aborted {
+        return String::from("Aborted");
+    }
+    let settings = versus::Settings {
+        rounds: rounds.max(1) as u32,
+        cells_per_turn: cells_per_turn.max(1) as u32,
+        generations_per_round: generations_per_round.max(1) as u32,
+    };
+
+    Map::clear(map);
+    let mut game = Versus::new(settings, map);
+
+    while !game.is_over() {
+        let round = game.scores.len() as u32 + 1;
+        for player in 0..versus::PLAYERS.len() {
+            if !place_cells(map, &game, round, player) {
+                return String::from("Two-player game aborted.");
+            }
+        }
+        game.record(map);
+
+        clear_console();
+        for generation in 1..=game.settings.generations_per_round {
+            *map = next_generation(map, &rule);
+            game.record(map);
+
+            refresh_console();
+            print_header(vec!["Game of Life", "Two-player game"]);
+            print_map_viewport(map, &Viewport::fit(map, PLAY_LINES, 0), false, false);
+            let message = format!(
+                "Round {round} of {} | Generation {generation} of {} | {}",
+                game.settings.rounds,
+                game.settings.generations_per_round,
+                colour_counts(map, rule.colours)
+            );
+            print_message(&fit_line(&message), true);
+
+            delay(game_properties.tick_rate);
+            match poll_play_events() {
+                PlayEvent::Abort => return String::from("Two-player game aborted."),
+                PlayEvent::Resized => clear_console(),
+                PlayEvent::Continue => (),
+            }
+        }
+        game.score_round(map);
+    }
+
+    // Summary screen
+    clear_console();
+    print_header(vec!["Game of Life", "Two-player game"]);
+    print_map_viewport(map, &Viewport::fit(map, MENU_LINES + game.scores.len() + 4, 0), false, false);
+    for line in game.summary() {
+        println!("{line}");
+    }
+    get_input(&String::from("Press Enter to go back to the menu."));
+
+    format!("[+] Two-player game over. {}", game.describe_outcome())
+}
+
+// The game needs two colours: the configured rule with Immigration on top
+// when it can have it, plain Immigration otherwise.
+fn versus_rule(rule: &Rule) -> Rule {
+    if rule.colours == 2 {
+        return rule.clone();
+    }
+    match Rule::parse(&format!("{} Immigration", rule.name)) {
+        Ok(rule) => rule,
+        Err(_) => Rule::parse("Immigration").unwrap(),
+    }
+}
+
+// One player's turn. Cells go on dead cells only. False if the game was
+// quit.
+fn place_cells(map: &mut Vectrix, game: &Versus, round: u32, player: usize) -> bool {
+    let name = Versus::player_name(player);
+    let colour = versus::PLAYERS[player];
+    let mut message = format!("{name}'s turn.");
+
+    let mut placed = 0;
+    while placed < game.settings.cells_per_turn {
+        clear_console();
+        print_header(vec!["Game of Life", "Two-player game"]);
+        print_map(map, true, true);
+        print_message(&message, true);
+        print_message(
+            &format!(
+                "Round {round} of {} | {name}: {} of {} cells left",
+                game.settings.rounds,
+                game.settings.cells_per_turn - placed,
+                game.settings.cells_per_turn
+            ),
+            true,
+        );
+
+        let Some(point) = get_coordinates("Cell") else {
+            return false;
+        };
+        let (row, col) = (point.row % map.len(), point.col % map[0].len());
+        match map[row][col] {
+            Cell::Dead(_) => {
+                map[row][col] = Cell::coloured(colour);
+                placed += 1;
+                message = format!("[+] {name} cell at [{row:>2}][{col:>2}]");
+            },
+            _ => message = format!("[-] [{row:>2}][{col:>2}] is taken."),
+        }
+    }
+    true
+}
+
+enum NetworkMenuOpt {
+    Host,
+    Join,
+    Watch,
+    Exit,
+    Unknown,
+}
+
+fn network_menu() -> NetworkMenuOpt {
+    let network_text: String = format!(
+        "{} | {} | {} | {}\n",
+        "1. Host a session",
+        "2. Join a session",
+        "3. Watch a game",
+        "99. Exit",
+    );
+    print_message(&network_text, true);
+
+    let opt = get_u32(&String::from("Option: "));
+
+    match opt {
+        1 => NetworkMenuOpt::Host,
+        2 => NetworkMenuOpt::Join,
+        3 => NetworkMenuOpt::Watch,
+        99 => NetworkMenuOpt::Exit,
+        _ => NetworkMenuOpt::Unknown,
+    }
+}
+
+// Host or join a session, see net.rs. Hosts share their map, peers get the
+// host's map when they join. Watching a game leaves this map alone.
+fn network_session(map: &mut Vectrix, game_properties: &mut GameConfig) -> String {
+    let (session, watching) = loop {
+        clear_console();
+        print_header(vec!["Game of Life", "Network session"]);
+        print_map(map, true, true);
+
+        match network_menu() {
+            NetworkMenuOpt::Host => {
+                let input = get_input(&format!("Port (empty for {}):", net::DEFAULT_PORT));
+                let port = input.trim().parse::<u16>().unwrap_or(net::DEFAULT_PORT);
+                break (Session::host(port), false);
+            },
+            NetworkMenuOpt::Join => break (join_session(net::DEFAULT_PORT), false),
+            NetworkMenuOpt::Watch => break (join_session(net::SPECTATOR_PORT), true),
+            NetworkMenuOpt::Exit => return String::from("Network session cancelled."),
+            NetworkMenuOpt::Unknown => (),
+        }
+    };
+
+    match session {
+        Ok(mut session) if watching => spectate::watch(&mut session),
+        Ok(mut session) => run_session(map, game_properties, &mut session),
+        Err(error) => format!("[-] Network session failed: {error}."),
+    }
+}
+
+// Asks where the host is, and `port` is used if no port is given.
+fn join_session(port: u16) -> Result<Session, String> {
+    let prompt = format!("Host address, like 192.168.1.20:{port} (empty for this computer):");
+    let input = get_input(&prompt);
+    let address = match input.trim() {
+        "" => String::from("127.0.0.1"),
+        address => address.to_string(),
+    };
+    let input = get_input(&String::from("Your name:"));
+    let name = match input.trim() {
+        "" => String::from("player"),
+        name => name.to_string(),
+    };
+    Session::join(&address, port, &name)
+}
+
+// Commands are typed while the map keeps updating with what the others do.
+fn run_session(map: &mut Vectrix, game_properties: &mut GameConfig, session: &mut Session) -> String {
+    let mut lockstep = Lockstep::new();
+    let mut message = format!("[+] {}", session.describe());
+    let mut redraw = true;
+
+    loop {
+        for event in session.poll() {
+            match event {
+                SessionEvent::Joined(peer, name) => {
+                    // Newcomers start from the host's map.
+                    let snapshot = Message::Snapshot {
+                        generation: lockstep.generation,
+                        map: map_text(map, &game_properties.rule),
+                    };
+                    session.send(peer, &snapshot);
+                    message = format!("[+] {name} joined.");
+                },
+                SessionEvent::Left(peer, name) => message = format!("[-] {name} (peer {peer}) left."),
+                SessionEvent::Closed(reason) => return format!("[-] Network session over: {reason}."),
+                SessionEvent::Received(peer, received) => {
+                    let news = match session.hosting {
+                        true => host_input(map, game_properties, session, &mut lockstep, peer, received),
+                        false => peer_input(map, game_properties, session, &mut lockstep, received),
+                    };
+                    match news {
+                        Some(news) => message = news,
+                        None => continue,
+                    }
+                },
+            }
+            redraw = true;
+        }
+
+        // Lets the peers check they are still in step.
+        if session.hosting && lockstep.hash_due() {
+            session.broadcast(&Message::Hash {
+                generation: lockstep.generation,
+                hash: net::state_hash(map, &game_properties.rule),
+            });
+        }
+
+        if redraw {
+            clear_console();
+            print_header(vec!["Game of Life", "Network session"]);
+            print_map(map, true, true);
+            print_message(&session.describe(), true);
+            let mut status = format!("Generation {} | {}", lockstep.generation, game_properties.rule.describe());
+            if lockstep.resyncing {
+                status.push_str(" | Out of sync");
+            }
+            if session.hosting && !session.peer_names().is_empty() {
+                status.push_str(&format!(" | Peers: {}", session.peer_names().join(", ")));
+            }
+            print_message(&fit_line(&status), true);
+            print_message(&message, true);
+            print_message(&String::from("s <row> <col>: set/clear cell | n [count]: next generations | r <rule>: change rule | q: leave"), true);
+            print_message(&String::from("Command:"), true);
+            redraw = false;
+        }
+
+        // Checks the network again every 100 ms while waiting for a command.
+        let Some(line) = read_line(Some(std::time::Duration::from_millis(100))) else {
+            continue;
+        };
+        redraw = true;
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let numbers: Vec<usize> = words.iter().skip(1).filter_map(|word| word.parse::<usize>().ok()).collect();
+        match (words.first().copied(), numbers.as_slice()) {
+            (Some("s"), [row, col]) => {
+                let (row, col) = (row % map.len(), col % map[0].len());
+                let cell = map[row][col].not();
+                let edit = Message::Edit {
+                    generation: lockstep.generation,
+                    row,
+                    col,
+                    state: cell.state(),
+                    colour: cell.colour(),
+                };
+                // Peers wait for the host to send it back, so everybody
+                // applies edits in the same order.
+                match session.hosting {
+                    true => {
+                        map[row][col] = cell;
+                        session.broadcast(&edit);
+                        message = format!("[+] Cell [{row:>2}][{col:>2}] changed.");
+                    },
+                    false => {
+                        session.broadcast(&edit);
+                        message = String::from("Edit sent to the host.");
+                    },
+                }
+            },
+            (Some("n"), counts) if counts.len() <= 1 => {
+                let generations = counts.first().copied().unwrap_or(1).clamp(1, net::MAX_STEP as usize) as u32;
+                match session.hosting {
+                    true => {
+                        message = step_session(map, game_properties, session, &mut lockstep, generations);
+                    },
+                    false => {
+                        session.broadcast(&Message::Step { generations });
+                        message = format!("Asked the host for {generations} generations.");
+                    },
+                }
+            },
+            (Some("r"), _) if words.len() > 1 => {
+                let text = words[1..].join(" ");
+                // The host may use its own .rule files, peers can only send
+                // what the host has too.
+                let parsed = match session.hosting {
+                    true => Rule::parse(&text),
+                    false => Rule::parse_remote(&text),
+                };
+                message = match parsed {
+                    Ok(rule) if session.hosting => match change_rule(map, game_properties, rule) {
+                        Ok(()) => {
+                            session.broadcast(&Message::Rule {
+                                generation: lockstep.generation,
+                                rule: game_properties.rule.name.clone(),
+                            });
+                            format!("[+] Rule = {}", game_properties.rule.describe())
+                        },
+                        Err(error) => format!("[-] Bad rule: {error}."),
+                    },
+                    Ok(rule) => {
+                        session.broadcast(&Message::Rule {
+                            generation: lockstep.generation,
+                            rule: rule.name,
+                        });
+                        String::from("Rule sent to the host.")
+                    },
+                    Err(error) => format!("[-] Bad rule: {error}."),
+                };
+            },
+            (Some("q"), []) => {
+                session.close();
+                return String::from("[+] Left the network session.");
+            },
+            (None, _) => (),
+            _ => message = String::from("[-] Unknown command."),
+        }
+    }
+}
+
+// Hosts: what an input from `peer` does. It is applied here and passed on to
+// everybody, the sender included. None when there's nothing to tell.
+fn host_input(
+    map: &mut Vectrix,
+    game_properties: &mut GameConfig,
+    session: &mut Session,
+    lockstep: &mut Lockstep,
+    peer: u32,
+    received: Message,
+) -> Option<String> {
+    let news = match received {
+        Message::Edit { row, col, state, colour, .. } => {
+            let (row, col) = (row % map.len(), col % map[0].len());
+            map[row][col] = network_cell(state, colour, &game_properties.rule);
+            // Passed on as it was applied here.
+            session.broadcast(&Message::Edit {
+                generation: lockstep.generation,
+                row,
+                col,
+                state: map[row][col].state(),
+                colour: map[row][col].colour(),
+            });
+            format!("[+] Peer {peer} changed cell [{row:>2}][{col:>2}].")
+        },
+        Message::Rule { rule, .. } => match Rule::parse_remote(&rule).and_then(|rule| change_rule(map, game_properties, rule)) {
+            Ok(()) => {
+                session.broadcast(&Message::Rule {
+                    generation: lockstep.generation,
+                    rule: game_properties.rule.name.clone(),
+                });
+                format!("[+] Peer {peer} changed the rule to {}.", game_properties.rule.describe())
+            },
+            Err(error) => format!("[-] Peer {peer} sent a bad rule: {error}."),
+        },
+        Message::Step { generations } => step_session(map, game_properties, session, lockstep, generations),
+        Message::Resync => {
+            let snapshot = Message::Snapshot {
+                generation: lockstep.generation,
+                map: map_text(map, &game_properties.rule),
+            };
+            session.send(peer, &snapshot);
+            format!("[-] Peer {peer} was out of sync, sent it the map.")
+        },
+        _ => String::from("[-] Unexpected message ignored."),
+    };
+    Some(news)
+}
+
+// Peers: what an input from the host does. Inputs for another generation, or
+// a hash that doesn't match, mean this computer is out of sync. None when
+// there's nothing to tell.
+fn peer_input(
+    map: &mut Vectrix,
+    game_properties: &mut GameConfig,
+    session: &mut Session,
+    lockstep: &mut Lockstep,
+    received: Message,
+) -> Option<String> {
+    if let Message::Snapshot { generation, map: text } = received {
+        let (received_map, rule) = match received_map(&text) {
+            Ok(received) => received,
+            // Asking again would only get the same map. Inputs are ignored
+            // until another one comes.
+            Err(error) => {
+                lockstep.resyncing = true;
+                return Some(format!("[-] Can't use the host's map: {error}."));
+            },
+        };
+        *map = received_map;
+        game_properties.rule = rule;
+        lockstep.generation = generation;
+        lockstep.resyncing = false;
+        return Some(format!("[+] Map from the host, generation {generation}."));
+    }
+    // The map on its way replaces everything anyway.
+    if lockstep.resyncing {
+        return None;
+    }
+
+    let generation = match &received {
+        Message::Edit { generation, .. } | Message::Rule { generation, .. } => *generation,
+        Message::Tick { generation, .. } | Message::Hash { generation, .. } => *generation,
+        _ => return Some(String::from("[-] Unexpected message ignored.")),
+    };
+    let in_step = generation == lockstep.generation
+        && match received {
+            Message::Hash { hash, .. } => hash == net::state_hash(map, &game_properties.rule),
+            _ => true,
+        };
+    if !in_step {
+        lockstep.resyncing = true;
+        session.broadcast(&Message::Resync);
+        return Some(format!("[-] Out of sync at generation {}, getting the map from the host.", lockstep.generation));
+    }
+
+    match received {
+        Message::Edit { row, col, state, colour, .. } => {
+            let (row, col) = (row % map.len(), col % map[0].len());
+            map[row][col] = network_cell(state, colour, &game_properties.rule);
+            Some(format!("[+] Cell [{row:>2}][{col:>2}] changed."))
+        },
+        Message::Rule { rule, .. } => match Rule::parse(&rule).and_then(|rule| change_rule(map, game_properties, rule)) {
+            Ok(()) => Some(format!("[+] Rule = {}", game_properties.rule.describe())),
+            // E.g. a .rule file this computer doesn't have. Can't follow the
+            // others without it.
+            Err(error) => {
+                lockstep.resyncing = true;
+                session.broadcast(&Message::Resync);
+                Some(format!("[-] Can't use the rule {rule}: {error}."))
+            },
+        },
+        Message::Tick { generations, .. } => {
+            // The host starts counting again at 0 and sends the map.
+            if !lockstep.advance(generations) {
+                lockstep.resyncing = true;
+                session.broadcast(&Message::Resync);
+                return Some(String::from("[-] The generation count is full, getting the map from the host."));
+            }
+            for _ in 0..generations {
+                *map = next_generation(map, &game_properties.rule);
+            }
+            Some(format!("[+] {generations} generations, now at {}.", lockstep.generation))
+        },
+        _ => None,
+    }
+}
+
+// The map and rule of a SNAPSHOT, or why they can't be used.
+fn received_map(text: &str) -> Result<(Vectrix, Rule), String> {
+    let (map, rule) = read_map(text)?;
+    if map.iter().any(|row| row.len() != map[0].len()) {
+        return Err(String::from("the rows aren't all the same length"));
+    }
+    Ok((map, rule))
+}
+
+// A cell sent over the network as its state and colour. States and colours
+// the rule doesn't have become its last one, like in a loaded map.
+fn network_cell(state: u8, colour: u8, rule: &Rule) -> Cell {
+    match colour.min(rule.colours) {
+        0 => Cell::for_rule(state.min(rule.states - 1), rule),
+        colour => Cell::coloured(colour.min(CELL_COLOURS.len() as u8)),
+    }
+}
+
+fn change_rule(map: &mut Vectrix, game_properties: &mut GameConfig, rule: Rule) -> Result<(), String> {
+    rule.fits(map.len())?;
+    game_properties.rule = rule;
+    fit_to_rule(map, &game_properties.rule);
+    Ok(())
+}
+
+// Hosts only: runs the universe, tells everybody to do the same and sends
+// the hash to check against.
+fn step_session(map: &mut Vectrix, game_properties: &GameConfig, session: &mut Session, lockstep: &mut Lockstep, generations: u32) -> String {
+    // A peer can ask for any number.
+    let generations = generations.min(net::MAX_STEP);
+    session.broadcast(&Message::Tick {
+        generation: lockstep.generation,
+        generations,
+    });
+    for _ in 0..generations {
+        *map = next_generation(map, &game_properties.rule);
+    }
+    // The count can't go on. It starts again at 0, and everybody gets the map
+    // to go on from.
+    if !lockstep.advance(generations) {
+        lockstep.generation = 0;
+        session.broadcast(&Message::Snapshot {
+            generation: 0,
+            map: map_text(map, &game_properties.rule),
+        });
+    }
+    session.broadcast(&Message::Hash {
+        generation: lockstep.generation,
+        hash: net::state_hash(map, &game_properties.rule),
+    });
+    format!("[+] {generations} generations, now at {}.", lockstep.generation)
+}
+
+// Runs the selection, or the whole map if nothing is selected, on the
+// unbounded plane and reports how it repeats.
+fn measure_speed(map: &Vectrix, region: Option<&Selection>, rule: &Rule) -> String {
+    // The plane only runs Life.
+    if !rule.is_life() {
+        return format!("[-] Speeds can only be measured under Life, not {}.", rule.name);
+    }
+    let whole_map = Selection::new(
+        map,
+        Coordinates::new(0, 0),
+        Coordinates::new(map.len() - 1, map[0].len() - 1),
+    );
+    let region = region.unwrap_or(&whole_map);
+
+    let points = Clipboard::copy(map, region).live_points();
+    if points.is_empty() {
+        return String::from("[-] Nothing to measure, no live cells.");
+    }
+
+    match Velocity::measure(&Plane::from_points(&points), speed::MAX_PERIOD) {
+        Some(velocity) => format!("[+] {}", velocity.describe()),
+        None => format!("[-] Doesn't repeat in {} generations.", speed::MAX_PERIOD),
+    }
+}
+
+// Save the selected region to the user pattern library.
+fn save_user_pattern(map: &Vectrix, region: &Selection) -> String {
+    // Patterns are Life patterns, only the live cells go in.
+    let copied = Clipboard::copy(map, region);
+    let points = copied.live_points();
+    if points.is_empty() {
+        return String::from("[-] Nothing to save, the selection is empty.");
+    }
+
+    let name = get_input(&String::from("Pattern name (empty to abort):"));
+    let name = name.trim();
+    if name.is_empty() {
+        return String::from("Save aborted.");
+    }
+
+    let path = pattern::library_path(name);
+    let overwrite = path.exists();
+    if overwrite {
+        let prompt = format!("{} already exists. Overwrite it? (y/n)", path.display());
+        if get_input(&prompt).trim() != "y" {
+            return String::from("Save aborted.");
+        }
+    }
+
+    let mut category_text = String::from("Category:");
+    for (number, category) in Category::ALL.iter().enumerate() {
+        category_text.push_str(&format!(" {}. {} |", number + 1, category.name()));
+    }
+    category_text.pop();
+    let category = match get_u32(&category_text) as usize {
+        number if (1..=Category::ALL.len()).contains(&number) => Category::ALL[number - 1],
+        _ => Category::Other,
+    };
+
+    let notes = get_input(&String::from("Notes (optional):"));
+    let notes = match notes.trim() {
+        "" => None,
+        notes => Some(notes.to_string()),
+    };
+
+    let pattern = Pattern {
+        name: name.to_string(),
+        category,
+        period: None,
+        velocity: None,
+        discoverer: None,
+        lifespan: None,
+        final_population: None,
+        notes,
+        user: true,
+        rows: copied.rows,
+        cols: copied.cols,
+        cells: points,
+    };
+    pattern::save_to_library(&pattern, overwrite)
+}
+
+fn select_region(map: &Vectrix) -> (Option<Selection>, String) {
+    let (top, aborted) = get_usize("Top row", true);
+    if aborted {
+        return (None, String::from("Selection cleared."));
+    }
+    let (left, aborted) = get_usize("Left col", true);
+    if aborted {
+        return (None, String::from("Selection cleared."));
+    }
+    let (bottom, aborted) = get_usize("Bottom row", true);
+    if aborted {
+        return (None, String::from("Selection cleared."));
+    }
+    let (right, aborted) = get_usize("Right col", true);
+    if aborted {
+        return (None, String::from("Selection cleared."));
+    }
+
+    let selection = Selection::new(map, Coordinates::new(top, left), Coordinates::new(bottom, right));
+    let message = format!("[+] Selected {}.", selection.describe());
+    (Some(selection), message)
+}
+
+// Ask for an origin, show where the clipboard would land and paste it if confirmed.
+fn paste_clipboard(map: &mut Vectrix, history: &mut History, clipboard: &Clipboard, mode: PasteMode) -> String {
+    let mut message = format!("Paste ({}). Set origin.", mode.name());
+
+    loop {
+        clear_console();
+        print_header(vec!["Game of Life"]);
+        print_map(map, true, true);
+        print_message(&message, true);
+
+        let (row, aborted) = get_usize("Row", true);
+        if aborted {
+            return String::from("Paste finished.");
+        }
+        let (col, aborted) = get_usize("Col", true);
+        if aborted {
+            return String::from("Paste finished.");
+        }
+        let origin = Coordinates::new(row, col);
+
+        clear_console();
+        print_header(vec!["Game of Life"]);
+        print_map(&clipboard.preview(map, &origin), true, true);
+        print_message(&String::from("Preview. `▒` cells will be pasted."), true);
+
+        let answer = get_input(&String::from("Paste here? (y/n)"));
+        if answer.trim() == "y" {
+            let before = map.clone();
+            message = clipboard.paste(map, &origin, mode);
+            history.record("Paste", &before, map);
+        } else {
+            message = String::from("Paste cancelled. Set origin.");
+        }
+    }
+}
+
+enum DrawMenuOpt {
+    Line,
+    Rectangle,
+    FilledRectangle,
+    Ellipse,
+    FilledEllipse,
+    FloodFill,
+    Pen,
+    Undo,
+    Redo,
+    Exit,
+    Unknown,
+}
+
+fn draw_menu() -> DrawMenuOpt {
+    let draw_text: String = format!(
+        "{} | {} | {} | {} | {} | {} | {} | {} | {} | {}\n",
+        "1. Line",
+        "2. Rectangle",
+        "3. Filled rectangle",
+        "4. Ellipse",
+        "5. Filled ellipse",
+        "6. Flood fill",
+        "7. Pen state",
+        "u. Undo",
+        "r. Redo",
+        "99. Exit",
+    );
+    print_message(&draw_text, true);
+
+    let opt = get_input(&String::from("Option: "));
+
+    match opt.trim() {
+        "1" => DrawMenuOpt::Line,
+        "2" => DrawMenuOpt::Rectangle,
+        "3" => DrawMenuOpt::FilledRectangle,
+        "4" => DrawMenuOpt::Ellipse,
+        "5" => DrawMenuOpt::FilledEllipse,
+        "6" => DrawMenuOpt::FloodFill,
+        "7" => DrawMenuOpt::Pen,
+        "u" => DrawMenuOpt::Undo,
+        "r" => DrawMenuOpt::Redo,
+        "99" => DrawMenuOpt::Exit,
+        _ => DrawMenuOpt::Unknown,
+    }
+}
+
+// Asks for a Row and a Col. None if aborted.
+fn get_coordinates(label: &str) -> Option<Coordinates> {
+    let (row, aborted) = get_usize(&format!("{label} row"), true);
+    if aborted {
+        return None;
+    }
+    let (col, aborted) = get_usize(&format!("{label} col"), true);
+    if aborted {
+        return None;
+    }
+    Some(Coordinates::new(row, col))
+}
+
+// "1 Red, 2 Blue" for the colours of a rule, to ask for one.
+fn colour_names(colours: u8) -> String {
+    let names: Vec<String> = CELL_COLOURS[..colours as usize]
+        .iter()
+        .enumerate()
+        .map(|(n, (name, _, _))| format!("{} {name}", n + 1))
+        .collect();
+    names.join(", ")
+}
+
+// Asks for density, region, symmetry, seed and, for coloured rules, colour
+// of a random soup. None if aborted.
+fn random_fill_settings(map: &Vectrix, rule: &Rule) -> Option<(Soup, Selection)> {
+    let (density, aborted) = get_usize("Density (% of live cells)", true);
+    if aborted {
+        return None;
+    }
+
+    let region = match get_input(&String::from("Fill the whole map? (y/n)")).trim() {
+        "n" => {
+            let (region, _) = select_region(map);
+            region?
+        },
+        _ => Selection::new(
+            map,
+            Coordinates::new(0, 0),
+            Coordinates::new(map.len() - 1, map[0].len() - 1),
+        ),
+    };
+
+    let symmetry_text = "Symmetry: 1. None | 2. C2 | 3. C4 | 4. D2 | 5. D4 | 6. D8";
+    let symmetry = match get_u32(&String::from(symmetry_text)) {
+        2 => Symmetry::C2,
+        3 => Symmetry::C4,
+        4 => Symmetry::D2,
+        5 => Symmetry::D4,
+        6 => Symmetry::D8,
+        _ => Symmetry::None,
+    };
+
+    // Same seed, same soup.
+    let seed = loop {
+        let input = get_input(&String::from("Seed (empty for a new one):"));
+        if input.trim().is_empty() {
+            break Rng::clock_seed();
+        }
+        match input.trim().parse::<u64>() {
+            Ok(seed) => break seed,
+            Err(_error) => print_message(&String::from("[-] Bad input. Try again."), true),
+        }
+    };
+
+    // Immigration and QuadLife cells need a colour to take part.
+    let colours = match rule.colours {
+        0 => vec![],
+        colours => {
+            let prompt = format!("Colour (0 none, {}, {} mixed):", colour_names(colours), colours + 1);
+            match get_u32(&prompt) {
+                0 => vec![],
+                colour if colour <= colours as u32 => vec![colour as u8],
+                _ => (1..=colours).collect(),
+            }
+        },
+    };
+
+    let soup = Soup {
+        seed,
+        density: density.min(100) as u32,
+        symmetry,
+        colours,
+    };
+    Some((soup, region))
+}
+
+// Lists the built-in patterns by category and asks for one. None if aborted.
+fn choose_pattern() -> Option<Pattern> {
+    let (mut patterns, errors) = pattern::full_catalog();
+    for error in errors {
+        print_message(&error, true);
+    }
+
+    let mut menu_text = String::new();
+    let mut category = None;
+    for (number, pattern) in patterns.iter().enumerate() {
+        if category != Some(pattern.category) {
+            category = Some(pattern.category);
+            menu_text.push_str(&format!("\n{}: ", pattern.category.name()));
+        } else {
+            menu_text.push_str(" | ");
+        }
+        menu_text.push_str(&format!("{}. {}", number + 1, pattern.name));
+        if pattern.user {
+            menu_text.push_str(" (yours)");
+        }
+    }
+    print_message(&menu_text, true);
+
+    let (opt, aborted) = get_usize("Pattern", true);
+    if aborted || opt == 0 || opt > patterns.len() {
+        return None;
+    }
+    Some(patterns.swap_remove(opt - 1))
+}
+
+// Shows the pattern and lets the user rotate and flip it. None if aborted.
+fn choose_orientation(pattern: &Pattern) -> Option<Orientation> {
+    let mut orientation = Orientation {
+        rotations: 0,
+        flipped: false,
+    };
+
+    loop {
+        let (shape, _) = Clipboard::from_points(pattern.points_at(&Coordinates::new(0, 0), &orientation));
+        let preview = shape.to_map();
+
+        clear_console();
+        print_header(vec!["Game of Life"]);
+        print_map(&preview, true, false);
+        print_message(&pattern.describe(), true);
+        print_message(&orientation.describe(), true);
+
+        let input = get_input(&String::from("`r` to rotate, `f` to flip, Enter to place it, `q` to quit."));
+        match input.trim() {
+            "r" => orientation.rotations = (orientation.rotations + 1) % 4,
+            "f" => orientation.flipped = !orientation.flipped,
+            "q" => return None,
+            "" => return Some(orientation),
+            _ => (),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
 enum Cell {
-    Alive(String),
+    // The number is the colour in Immigration and QuadLife, 0 for none.
+    Alive(u8, String),
     Dead(String),
+    // Generations rules: a cell fading out after it stopped being alive.
+    // The number is its state, from 2 up to the number of states - 1.
+    // Rule tables use it for their other states too.
+    Dying(u8, String),
 }
 
+// Glyphs of the dying states, fading out. Older states keep the last one.
+const DYING_GLYPHS: [&str; 3] = ["▓", "▒", "░"];
+// Colours of the dying states (256 colour palette), yellow to dark red.
+const DYING_COLOURS: [u8; 6] = [226, 214, 208, 202, 160, 88];
+// Colours of Immigration and QuadLife cells: name, letter in saved maps
+// and colour (256 colour palette). Immigration uses the first two.
+const CELL_COLOURS: [(&str, char, u8); 4] = [
+    ("Red", 'r', 196),
+    ("Blue", 'b', 33),
+    ("Green", 'g', 46),
+    ("Yellow", 'y', 226),
+];
+
 impl Cell {
     fn alive() -> Cell {
-        Cell::Alive(String::from("■"))
+        Cell::Alive(0, String::from("■"))
+    }
+    // Colour from 1 to 4, see CELL_COLOURS.
+    fn coloured(colour: u8) -> Cell {
+        let (_, _, code) = CELL_COLOURS[colour as usize - 1];
+        Cell::Alive(colour, format!("\x1b[38;5;{code}m■\x1b[0m"))
     }
     fn dead() -> Cell {
         Cell::Dead(String::from(" "))
     }
+    // Dying cells are coloured by age.
+    fn dying(state: u8) -> Cell {
+        let glyph = DYING_GLYPHS[(state as usize - 2).min(DYING_GLYPHS.len() - 1)];
+        let colour = DYING_COLOURS[(state as usize - 2).min(DYING_COLOURS.len() - 1)];
+        Cell::Dying(state, format!("\x1b[38;5;{colour}m{glyph}\x1b[0m"))
+    }
+    // A cell in the colour its rule gives the state, if it gives one.
+    // Dead cells stay blank.
+    fn for_rule(state: u8, rule: &Rule) -> Cell {
+        match (state, rule.colour(state)) {
+            (0, _) | (_, None) => Cell::with_state(state),
+            (1, Some((r, g, b))) => Cell::Alive(0, format!("\x1b[38;2;{r};{g};{b}m■\x1b[0m")),
+            (state, Some((r, g, b))) => Cell::Dying(state, format!("\x1b[38;2;{r};{g};{b}m■\x1b[0m")),
+        }
+    }
+    // What is drawn on screen.
+    fn glyph(&self) -> String {
+        match self {
+            Cell::Alive(_, ch) | Cell::Dead(ch) | Cell::Dying(_, ch) => ch.clone(),
+        }
+    }
+    // 0 for cells without a colour.
+    fn colour(&self) -> u8 {
+        match self {
+            Cell::Alive(colour, _) => *colour,
+            _ => 0,
+        }
+    }
+    // 0 dead, 1 alive, 2 and up dying.
+    fn state(&self) -> u8 {
+        match self {
+            Cell::Dead(_) => 0,
+            Cell::Alive(..) => 1,
+            Cell::Dying(state, _) => *state,
+        }
+    }
+    fn with_state(state: u8) -> Cell {
+        match state {
+            0 => Cell::dead(),
+            1 => Cell::alive(),
+            state => Cell::dying(state),
+        }
+    }
+    // The state after this one when a cell is not alive anymore.
+    fn decay(&self, rule: &Rule) -> Cell {
+        match self.state().checked_add(1) {
+            Some(next) if next < rule.states => Cell::dying(next),
+            _ => Cell::dead(),
+        }
+    }
     fn not(&self) -> Cell {
         match self {
-            Cell::Alive(_) => Cell::dead(),
+            Cell::Alive(..) => Cell::dead(),
             Cell::Dead(_) => Cell::alive(),
+            Cell::Dying(..) => Cell::dead(),
         }
     }
 }
 
-fn get_usize(prompt: &String, abort_feature: bool) -> (usize, bool) {
+fn get_usize(prompt: &str, abort_feature: bool) -> (usize, bool) {
     const ABORTED: bool = true;
     
-    let mut prompt_mod = prompt.clone();
+    let mut prompt_mod = prompt.to_string();
     if abort_feature {
         prompt_mod.push_str("\nUse `q` to quit.");
     }
@@ -275,7 +1668,7 @@ fn get_usize(prompt: &String, abort_feature: bool) -> (usize, bool) {
         
         // Check result str first to match `q`. If yes, abort.
         if input_trim == "q" {
-            return (0 as usize, ABORTED);
+            return (0_usize, ABORTED);
         }
     
         match input_trim.parse::<usize>() {
@@ -289,6 +1682,32 @@ fn get_usize(prompt: &String, abort_feature: bool) -> (usize, bool) {
     }
 }
 
+enum EditInput {
+    Value(usize),
+    Undo,
+    Redo,
+    Quit,
+}
+
+// Like get_usize(), but `u` and `r` undo and redo the last edit.
+fn get_edit_usize(prompt: &str) -> EditInput {
+    let prompt_mod = format!("{prompt}\nUse `q` to quit, `u` to undo, `r` to redo.");
+
+    loop {
+        let input_str = get_input(&prompt_mod);
+
+        match input_str.trim() {
+            "q" => return EditInput::Quit,
+            "u" => return EditInput::Undo,
+            "r" => return EditInput::Redo,
+            input_trim => match input_trim.parse::<usize>() {
+                Ok(value) => return EditInput::Value(value),
+                Err(_error) => print_message(&String::from("[-] Bad input. Try again."), true),
+            },
+        }
+    }
+}
+
 fn get_u32(prompt: &String) -> u32 {
     loop {
         match get_input(prompt).trim().parse::<u32>() {
@@ -303,17 +1722,61 @@ fn get_u32(prompt: &String) -> u32 {
 }
 
 fn get_input(prompt: &String) -> String {
-    use std::io;
-    let mut input = String::new();
-    print_message(&prompt, true);
-    match io::stdin().read_line(&mut input) {
-        Ok(_bytes_read) => {
-            return input;
-        },
-        Err(error) => {
-            panic!("[-] Failed to read input. Error details: {error}")
+    print_message(prompt, true);
+    read_line(None).unwrap_or_default()
+}
+
+// Lines typed by the user are read by a thread, so a network session can
+// wait for the keyboard and the network at the same time. The thread only
+// reads when a line is asked for, so crossterm still gets the keys (like
+// ESC) while playing.
+struct InputReader {
+    requests: std::sync::mpsc::Sender<()>,
+    lines: std::sync::mpsc::Receiver<String>,
+    // A line was asked for and hasn't been taken yet.
+    waiting: bool,
+}
+
+static INPUT: std::sync::OnceLock<std::sync::Mutex<InputReader>> = std::sync::OnceLock::new();
+
+// The next line typed, or None if nothing was typed within `timeout`.
+// Without a timeout it waits as long as it takes.
+fn read_line(timeout: Option<std::time::Duration>) -> Option<String> {
+    use std::sync::mpsc::{channel, RecvTimeoutError};
+
+    let input = INPUT.get_or_init(|| {
+        let (requests, request_receiver) = channel::<()>();
+        let (line_sender, lines) = channel::<String>();
+        std::thread::spawn(move || {
+            for _ in request_receiver {
+                let mut input = String::new();
+                match std::io::stdin().read_line(&mut input) {
+                    Ok(_bytes_read) => (),
+                    Err(error) => panic!("[-] Failed to read input. Error details: {error}"),
+                }
+                if line_sender.send(input).is_err() {
+                    break;
+                }
+            }
+        });
+        std::sync::Mutex::new(InputReader { requests, lines, waiting: false })
+    });
+    let mut input = input.lock().unwrap();
+
+    if !input.waiting {
+        input.requests.send(()).ok()?;
+        input.waiting = true;
+    }
+    let line = match timeout {
+        Some(timeout) => match input.lines.recv_timeout(timeout) {
+            Ok(line) => line,
+            Err(RecvTimeoutError::Timeout) => return None,
+            Err(RecvTimeoutError::Disconnected) => String::new(),
         },
+        None => input.lines.recv().unwrap_or_default(),
     };
+    input.waiting = false;
+    Some(line)
 }
 
 fn print_message(message: &String, new_line: bool) {
@@ -328,15 +1791,17 @@ fn print_message(message: &String, new_line: bool) {
 }
 
 fn print_header(header_contents: Vec<&str>) {
+    // Never wider than the terminal, otherwise the decor wraps.
+    let (term_cols, _) = terminal_size();
     let mut decor = String::new();
-    for _ in 0..70 {
+    for _ in 0..term_cols.min(70) {
         decor.push('=');
     }
     println!("{decor}");
     for piece in header_contents {
         print!("{} ", piece);
     }
-    print!("\n");
+    println!();
     println!("{decor}");
 }
 
@@ -350,49 +1815,234 @@ fn refresh_console() {
     print!("\x1b[0;0H");
 }
 
-fn print_map(map: &Vectrix, brackets: bool, headers: bool) {
-    let rows: usize = map.len();
-    let cols: usize = map[0].len();
+// Returns (columns, rows) of the terminal.
+// If there is no terminal (output redirected), or it doesn't know its size,
+// there is no limit.
+fn terminal_size() -> (usize, usize) {
+    match crossterm::terminal::size() {
+        Ok((cols, rows)) if cols > 0 && rows > 0 => (cols as usize, rows as usize),
+        _ => (usize::MAX, usize::MAX),
+    }
+}
 
-    let mut header: String;
+// Lines used around the map by the header, messages and menus.
+const MENU_LINES: usize = 11;
+// Lines used around the map by the header and status line while playing.
+const PLAY_LINES: usize = 5;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Density {
+    // Four characters per cell: `[■ ]`, with row and column headers.
+    Full,
+    // One character per cell.
+    Compact,
+    // One character for two rows of cells, using half blocks.
+    HalfBlock,
+}
 
-    // Column header print
-    for i in 0..cols {
-        // Top left corner is blank
-        if i == 0 {
-            print!("    ");
-        }
-        header = match headers {
-            true => format!("[{i:>2}]"),
-            false => format!("    "),
+// The part of the map that is drawn on screen.
+struct Viewport {
+    top: usize,
+    left: usize,
+    rows: usize,
+    cols: usize,
+    density: Density,
+    // Hexagonal rules: odd rows are shifted half a cell to the right.
+    // Only the full layout has room for that.
+    hex: bool,
+}
+
+impl Viewport {
+    // Pick the densest layout that shows the whole map in the terminal.
+    // If even half blocks don't fit, the map is clipped.
+    // `reserved_cols` are kept free on the right, e.g. for the statistics panel.
+    fn fit(map: &Vectrix, reserved_lines: usize, reserved_cols: usize) -> Viewport {
+        let (term_cols, term_rows) = terminal_size();
+        let term_cols = term_cols.saturating_sub(reserved_cols).max(1);
+        let map_rows = map.len();
+        let map_cols = map[0].len();
+
+        // One line is kept for the "Showing rows..." note when clipping.
+        let lines = term_rows.saturating_sub(reserved_lines + 1).max(1);
+
+        // Full density needs an extra line for the column header.
+        let density = if map_rows < lines && 4 + 4 * map_cols <= term_cols {
+            Density::Full
+        } else if map_rows <= lines && map_cols <= term_cols {
+            Density::Compact
+        } else {
+            Density::HalfBlock
         };
-        print!("{header}");
+
+        let (rows, cols) = match density {
+            Density::Full | Density::Compact => (map_rows, map_cols),
+            Density::HalfBlock => (map_rows.min(lines * 2), map_cols.min(term_cols)),
+        };
+
+        Viewport {
+            top: 0,
+            left: 0,
+            rows,
+            cols,
+            density,
+            hex: false,
+        }
     }
-    println!();
 
-    // Map print
-    for i in 0..rows {
-        // Row header print
-        header = match headers {
-            true => format!("[{i:>2}]"),
-            false => format!("    "),
+    // Like `fit`, but with the layout given, starting at `top`, `left`.
+    // Spectators pick these themselves.
+    fn zoomed(map: &Vectrix, density: Density, top: usize, left: usize, reserved_lines: usize) -> Viewport {
+        let (term_cols, term_rows) = terminal_size();
+        let lines = term_rows.saturating_sub(reserved_lines + 1).max(1);
+
+        let (rows, cols) = match density {
+            Density::Full => (lines.saturating_sub(1).max(1), (term_cols.saturating_sub(4) / 4).max(1)),
+            Density::Compact => (lines, term_cols),
+            Density::HalfBlock => (lines.saturating_mul(2), term_cols),
         };
-        print!("{header}");
+        let rows = rows.min(map.len());
+        let cols = cols.min(map[0].len());
+
+        Viewport {
+            top: top.min(map.len() - rows),
+            left: left.min(map[0].len() - cols),
+            rows,
+            cols,
+            density,
+            hex: false,
+        }
+    }
 
-        for j in 0..cols {
-            let ch = match &map[i][j] {
-                Cell::Alive(ch) => ch,
-                Cell::Dead(ch) => ch,
-            };
-            if brackets {
-                print!("[{ch} ]");
+    fn is_clipped(&self, map: &Vectrix) -> bool {
+        self.rows < map.len() || self.cols < map[0].len()
+    }
+}
+
+fn print_map(map: &Vectrix, brackets: bool, headers: bool) {
+    let viewport = Viewport::fit(map, MENU_LINES, 0);
+    print_map_viewport(map, &viewport, brackets, headers);
+}
+
+fn print_map_viewport(map: &Vectrix, viewport: &Viewport, brackets: bool, headers: bool) {
+    for line in render_map(map, viewport, brackets, headers) {
+        println!("{line}");
+    }
+    print_clip_note(map, viewport);
+}
+
+fn print_clip_note(map: &Vectrix, viewport: &Viewport) {
+    if viewport.is_clipped(map) {
+        println!(
+            "Showing rows {}-{}, cols {}-{} of {} x {}",
+            viewport.top,
+            viewport.top + viewport.rows - 1,
+            viewport.left,
+            viewport.left + viewport.cols - 1,
+            map.len(),
+            map[0].len(),
+        );
+    }
+}
+
+// Builds the lines of the map without printing them.
+fn render_map(map: &Vectrix, viewport: &Viewport, brackets: bool, headers: bool) -> Vec<String> {
+    let mut lines = Vec::<String>::new();
+    let row_range = viewport.top..viewport.top + viewport.rows;
+    let col_range = viewport.left..viewport.left + viewport.cols;
+
+    match viewport.density {
+        Density::Full => {
+            // Column header. Top left corner is blank.
+            let mut line = String::from("    ");
+            for j in col_range.clone() {
+                match headers {
+                    true => line.push_str(&format!("[{j:>2}]")),
+                    false => line.push_str("    "),
+                };
             }
-            if !brackets {
-                print!(" {ch}  ");
+            lines.push(line);
+
+            for i in row_range {
+                // Row header
+                let mut line = match headers {
+                    true => format!("[{i:>2}]"),
+                    false => String::from("    "),
+                };
+                if viewport.hex && i % 2 == 1 {
+                    line.push_str("  ");
+                }
+                for cell in &map[i][col_range.clone()] {
+                    let ch = cell.glyph();
+                    match brackets {
+                        true => line.push_str(&format!("[{ch} ]")),
+                        false => line.push_str(&format!(" {ch}  ")),
+                    };
+                }
+                lines.push(line);
+            }
+        },
+        Density::Compact => {
+            for i in row_range {
+                let mut line = String::new();
+                for cell in &map[i][col_range.clone()] {
+                    line.push_str(&cell.glyph());
+                }
+                lines.push(line);
+            }
+        },
+        Density::HalfBlock => {
+            for i in row_range.step_by(2) {
+                let mut line = String::new();
+                for j in col_range.clone() {
+                    // Dying cells and table states count as filled too.
+                    let top = !matches!(map[i][j], Cell::Dead(_));
+                    let bottom = i + 1 < viewport.top + viewport.rows
+                        && !matches!(map[i + 1][j], Cell::Dead(_));
+                    line.push(match (top, bottom) {
+                        (true, true) => '█',
+                        (true, false) => '▀',
+                        (false, true) => '▄',
+                        (false, false) => ' ',
+                    });
+                }
+                lines.push(line);
             }
+        },
+    }
+    lines
+}
+
+// Prints two blocks of lines side by side, `gap` spaces apart.
+fn print_beside(left: Vec<String>, right: Vec<String>, gap: usize) {
+    let width = left.iter().map(|line| visible_width(line)).max().unwrap_or(0);
+
+    for i in 0..left.len().max(right.len()) {
+        let left_line = left.get(i).map(String::as_str).unwrap_or("");
+        let right_line = right.get(i).map(String::as_str).unwrap_or("");
+        let padding = width - visible_width(left_line) + gap;
+        println!("{left_line}{}{right_line}", " ".repeat(padding));
+    }
+}
+
+// Characters that take space on screen, without the colour codes.
+fn visible_width(line: &str) -> usize {
+    let mut width = 0;
+    let mut in_escape = false;
+    for ch in line.chars() {
+        match ch {
+            '\x1b' => in_escape = true,
+            'm' if in_escape => in_escape = false,
+            _ if in_escape => (),
+            _ => width += 1,
         }
-        println!();
     }
+    width
+}
+
+// Cuts a message so it doesn't wrap onto the next line.
+fn fit_line(message: &str) -> String {
+    let (term_cols, _) = terminal_size();
+    message.chars().take(term_cols).collect()
 }
 
 fn set_generations() -> (u32, String) {
@@ -400,84 +2050,251 @@ fn set_generations() -> (u32, String) {
     
     clear_console();
 
-    return (
+    (
         generations, 
         format!("Generations = {generations}")
-    );
+    )
 }
 
 fn set_tick_rate() -> (u32, String) {
     let rate: u32 = get_u32(&String::from("Tick rate (ms): ")); 
-    return (
+    (
         rate, 
         format!("Tick rate = {rate} ms")
-    );
+    )
+}
+
+// How the universe ended up, and since which generation.
+enum Stability {
+    Dead(u32),
+    Still(u32),
+    Cycle(Cycle),
+}
+
+impl Stability {
+    fn describe(&self) -> String {
+        match self {
+            Stability::Dead(generation) => format!("Universe died at generation {generation}."),
+            Stability::Still(generation) => format!("Universe stable since generation {generation}."),
+            Stability::Cycle(cycle) => cycle.describe(),
+        }
+    }
+
+    fn stops(&self, game_properties: &GameConfig) -> bool {
+        match self {
+            Stability::Dead(_) | Stability::Still(_) => game_properties.stop_when_stable,
+            Stability::Cycle(_) => game_properties.stop_on_cycle,
+        }
+    }
 }
 
-fn play(map: &mut Vectrix, game_properties: &GameConfig) -> String {
+fn play(map: &mut Vectrix, game_properties: &GameConfig, spectators: &mut Option<Spectators>) -> (Statistics, String) {
     let mut generations: u32 = 0;
-    let i_size: usize = map.len();
-    let j_size: usize = map[0].len();
+    let mut statistics = Statistics::new();
+    statistics.record(generations, None, map);
+    let mut stability: Option<Stability> = None;
+    let mut cycle_detector = CycleDetector::new(game_properties.cycle_window);
+
+    // E.g. undoing a resize back to an odd number of rows under a hexagonal rule.
+    if let Err(error) = game_properties.rule.fits(map.len()) {
+        return (statistics, format!("[-] Can't play: {error}."));
+    }
     
     clear_console();
 
     loop {
+        // An empty universe stays empty.
+        if stability.is_none() && population(map) == 0 {
+            stability = Some(Stability::Dead(generations));
+        }
+        // Period 1 without moving is a still life, found below already.
+        if let Some(cycle) = cycle_detector.observe(generations, map)
+            && stability.is_none()
+            && (cycle.period > 1 || cycle.shift != (0, 0))
+        {
+            stability = Some(Stability::Cycle(cycle));
+        }
+
+        if let Some(spectators) = spectators {
+            spectators.show(generations, map, &game_properties.rule);
+        }
+
         refresh_console();
         print_header(vec!["Game of Life"]);
-        print_map(map, false, false);
-        let message = match game_properties.infinite_game {
+        // Shifted rows take 2 more columns.
+        let hex = game_properties.rule.neighbourhood == Neighbourhood::Hexagonal;
+        let hex_cols = if hex { 2 } else { 0 };
+        if game_properties.show_statistics {
+            // Map on the left, panel on the right, with a gap of 3.
+            let mut viewport = Viewport::fit(map, PLAY_LINES, PANEL_COLS + 3 + hex_cols);
+            viewport.hex = hex;
+            print_beside(render_map(map, &viewport, false, false), statistics.panel(), 3);
+            print_clip_note(map, &viewport);
+        } else {
+            let mut viewport = Viewport::fit(map, PLAY_LINES, hex_cols);
+            viewport.hex = hex;
+            print_map_viewport(map, &viewport, false, false);
+        }
+        let mut message = match game_properties.infinite_game {
             true => format!("Generation {}", generations),
             false => format!("Generation {} of {}", generations, game_properties.max_generations),
         };
+        if !game_properties.rule.is_life() {
+            message.push_str(&format!(" | {}", game_properties.rule.name));
+        }
+        if game_properties.rule.colours > 0 {
+            message.push_str(&format!(" | {}", colour_counts(map, game_properties.rule.colours)));
+        }
+        if let Some(stable) = &stability {
+            message.push_str(&format!(" | {}", stable.describe()));
+        }
+        if let Some(spectators) = spectators {
+            message.push_str(&format!(" | {} watching", spectators.count()));
+        }
         
-        print_message(&message, true);
+        print_message(&fit_line(&message), true);
+
+        if let Some(stable) = &stability
+            && stable.stops(game_properties)
+        {
+            let mut message = format!("Game finished. {}", stable.describe());
+            // Settled down, so the census means something now.
+            // The catalog is all Life patterns, other rules have nothing to match.
+            if game_properties.rule.is_life() {
+                message.push_str(&format!(" {}", Census::take(map).describe()));
+            }
+            return (statistics, message);
+        }
+
         delay(game_properties.tick_rate);
 
         // Just needed that extra print of the last generation.
         // Break the loop now.
         if generations == game_properties.max_generations && !game_properties.infinite_game {
             // Return message
-            return String::from("Game finished.");
+            let message = match &stability {
+                Some(stable) => format!("Game finished. {}", stable.describe()),
+                None => String::from("Game finished."),
+            };
+            return (statistics, message);
         }
 
-        // Return if ESC is pressed
-        if esc_key_pressed() {
-            return String::from("Game aborted.");
+        match poll_play_events() {
+            // Return if ESC is pressed
+            PlayEvent::Abort => return (statistics, String::from("Game aborted.")),
+            // Old frame is garbage after a resize. Start from a clean screen.
+            PlayEvent::Resized => clear_console(),
+            PlayEvent::Continue => (),
         }
         
-        let mut next_map: Vectrix = vec![vec![Cell::dead(); j_size]; i_size];
-        
-        for i in 0..i_size {
-            for j in 0..j_size {
-                let neighbors = calculate_neighbors(map, &i, &j);
-                calculate_next_gen(&map, &mut next_map, neighbors, &i, &j);
-            }
+        let next_map = next_generation(map, &game_properties.rule);
+        // Nothing changed: this generation is a still life.
+        if stability.is_none() && next_map == *map {
+            stability = Some(Stability::Still(generations));
         }
+        statistics.record(generations + 1, Some(map), &next_map);
         *map = next_map;
         generations += 1;
     }
     
 }
 
-fn calculate_next_gen(map: &Vectrix, next_map: &mut Vectrix, neighbors: u32, i: &usize, j: &usize) {
-    match &map[*i][*j] {
-        Cell::Alive(_) => {
-            // 1. Any live cell with fewer than 2 live neighbors dies, as if by underpopulation.
-            if neighbors < 2 {
-                next_map[*i][*j] = Cell::dead();
-            }
-            // 2. Any live cell with 2 or 3 live neighbors lives on to the next generation.
-            if neighbors == 2 || neighbors == 3 {
-                next_map[*i][*j] = Cell::alive();
+fn next_generation(map: &Vectrix, rule: &Rule) -> Vectrix {
+    let i_size: usize = map.len();
+    let j_size: usize = map[0].len();
+
+    let mut next_map: Vectrix = vec![vec![Cell::dead(); j_size]; i_size];
+
+    if let Some(table) = &rule.table {
+        let mut lookup = Lookup::default();
+        for (i, row) in next_map.iter_mut().enumerate() {
+            for (j, cell) in row.iter_mut().enumerate() {
+                *cell = calculate_table_state(map, table, rule, &mut lookup, &i, &j);
             }
-            // 3. Any live cell with more than 3 live neighbors dies, as if by overpopulation.
-            if neighbors > 3 {
-                next_map[*i][*j] = Cell::dead();
+        }
+        return next_map;
+    }
+
+    // Larger than Life neighbourhoods are too big to visit cell by cell,
+    // they are counted for the whole map at once.
+    let range_counts = match rule.neighbourhood {
+        Neighbourhood::Range { radius, shape, middle } => Some(ltl::counts(map, radius, shape, middle)),
+        _ => None,
+    };
+
+    // Only hexagonal neighbourhoods are different on odd rows.
+    let even_offsets = rule.neighbourhood.offsets(false);
+    let odd_offsets = rule.neighbourhood.offsets(true);
+    
+    for i in 0..i_size {
+        let offsets = match i % 2 {
+            0 => &even_offsets,
+            _ => &odd_offsets,
+        };
+        for j in 0..j_size {
+            let neighbors = match &range_counts {
+                Some(counts) => counts[i][j],
+                None if rule.non_totalistic => calculate_configuration(map, &i, &j),
+                None => calculate_neighbors(map, offsets, &i, &j),
+            };
+            calculate_next_gen(map, &mut next_map, neighbors, rule, &i, &j);
+
+            // Immigration and QuadLife: newborns take their parents' colour.
+            if rule.colours > 0 && matches!(map[i][j], Cell::Dead(_)) && matches!(next_map[i][j], Cell::Alive(..)) {
+                next_map[i][j] = match newborn_colour(map, offsets, rule.colours, &i, &j) {
+                    0 => Cell::alive(),
+                    colour => Cell::coloured(colour),
+                };
             }
+        }
+    }
+    next_map
+}
+
+// Live cells of every colour, e.g. "Red 12  Blue 9".
+fn colour_counts(map: &Vectrix, colours: u8) -> String {
+    let mut counts = [0usize; CELL_COLOURS.len() + 1];
+    for cell in map.iter().flatten() {
+        if let Cell::Alive(colour, _) = cell {
+            counts[*colour as usize] += 1;
+        }
+    }
+
+    let mut parts: Vec<String> = (1..=colours as usize)
+        .map(|colour| format!("{} {}", CELL_COLOURS[colour - 1].0, counts[colour]))
+        .collect();
+    if counts[0] > 0 {
+        parts.push(format!("No colour {}", counts[0]));
+    }
+    parts.join("  ")
+}
+
+// Dying cells count too, the universe isn't dead until they are gone.
+fn population(map: &Vectrix) -> usize {
+    map.iter()
+        .flatten()
+        .filter(|cell| !matches!(cell, Cell::Dead(_)))
+        .count()
+}
+
+fn calculate_next_gen(map: &Vectrix, next_map: &mut Vectrix, neighbors: u32, rule: &Rule, i: &usize, j: &usize) {
+    match &map[*i][*j] {
+        Cell::Alive(..) => {
+            // 1. Any live cell with a number of neighbors in the survival list lives on.
+            // 2. Any other live cell dies, or starts dying in Generations rules.
+            next_map[*i][*j] = match rule.survives(neighbors) {
+                // Keeps its colour, if it has one.
+                true => map[*i][*j].clone(),
+                false => map[*i][*j].decay(rule),
+            };
+        },
+        Cell::Dying(..) => {
+            // 3. Dying cells can't be saved. They get older until they are dead.
+            next_map[*i][*j] = map[*i][*j].decay(rule);
         },
         Cell::Dead(_) => {
-            // 4. Any dead cell with exactly 3 live neighbors becomes a live cell, as if by reproduction.
-            if neighbors == 3 {
+            // 4. Any dead cell with a number of neighbors in the birth list becomes a live cell.
+            if rule.born(neighbors) {
                 next_map[*i][*j] = Cell::alive();
             }
         },
@@ -485,171 +2302,143 @@ fn calculate_next_gen(map: &Vectrix, next_map: &mut Vectrix, neighbors: u32, i:
 
 }
 
-fn calculate_neighbors(map: &Vectrix, i: &usize, j: &usize) -> u32 {
-    let i_last: i32 = (map.len() - 1) as i32;
-    let j_last: i32 = (map[0].len() - 1) as i32;
-    
-    // This is needed because you cannot make usize integers go negative.
-    let i: i32 = *i as i32;
-    let j: i32 = *j as i32;
-    
-    let mut i_chk: i32;
-    let mut j_chk: i32;
-    let mut i_chk_ptr: usize;
-    let mut j_chk_ptr: usize;
-    
+// Live cells around [i][j], each counted with its weight.
+// `offsets` come from the rule's neighbourhood, Moore being:
+//
+// [i-1,j-1]  [i-1,_j_]  [i-1,j+1]
+// [_i_,j-1]  [_i_,_j_]  [_i_,j+1]
+// [i+1,j-1]  [i+1,_j_]  [i+1,j+1]
+//
+// The map is a torus: a neighbour past an edge is on the other side.
+fn calculate_neighbors(map: &Vectrix, offsets: &[(i64, i64, u32)], i: &usize, j: &usize) -> u32 {
+    let i_size = map.len() as i64;
+    let j_size = map[0].len() as i64;
+
     let mut neighbors = 0;
-    
-    // [i-1,j-1]  [i-1,_j_]  [i-1,j+1]
-    // [_i_,j-1]  [_i_,_j_]  [_i_,j+1]
-    // [i+1,j-1]  [i+1,_j_]  [i+1,j+1]
-
-    // [i-1,j-1] : NW
-    // [i-1,_j_] : N
-    // [i-1,j+1] : NE
-
-    // [_i_,j-1] : W
-    // [_i_,j+1] : E
-
-    // [i+1,j-1] : SW
-    // [i+1,_j_] : S
-    // [i+1,j+1] : SE
-
-    // [i-1,j-1] : NW
-    i_chk = i - 1;
-    j_chk = j - 1;
-    if i_chk < 0 {
-        i_chk = i_last;
-    }
-    if j_chk < 0 {
-        j_chk = j_last;
-    }
-    i_chk_ptr = i_chk as usize;
-    j_chk_ptr = j_chk as usize;
-    neighbors = match map[i_chk_ptr][j_chk_ptr] {
-        Cell::Alive(_) => neighbors + 1,
-        Cell::Dead(_) => neighbors,
-    };
+    for (d_row, d_col, weight) in offsets {
+        let i_chk = (*i as i64 + d_row).rem_euclid(i_size) as usize;
+        let j_chk = (*j as i64 + d_col).rem_euclid(j_size) as usize;
+        if let Cell::Alive(..) = map[i_chk][j_chk] {
+            neighbors += weight;
+        }
+    }
+    neighbors
+}
 
-    // [i-1,_j_] : N
-    i_chk = i - 1;
-    j_chk = j;
-    if i_chk < 0 {
-        i_chk = i_last;
-    }
-    i_chk_ptr = i_chk as usize;
-    j_chk_ptr = j_chk as usize;
-    neighbors = match map[i_chk_ptr][j_chk_ptr] {
-        Cell::Alive(_) => neighbors + 1,
-        Cell::Dead(_) => neighbors,
-    };
+// The colour most live neighbours of [i][j] have, each counted with its
+// weight. With 4 colours, 3 neighbours of 3 different colours give the one
+// none of them has. Other ties are broken by the cell's position, so no
+// colour wins them all. 0 if no neighbour has a colour.
+fn newborn_colour(map: &Vectrix, offsets: &[(i64, i64, u32)], colours: u8, i: &usize, j: &usize) -> u8 {
+    let i_size = map.len() as i64;
+    let j_size = map[0].len() as i64;
+
+    // parents[c] = neighbours of colour c.
+    let mut parents = [0u32; CELL_COLOURS.len() + 1];
+    for (d_row, d_col, weight) in offsets {
+        let i_chk = (*i as i64 + d_row).rem_euclid(i_size) as usize;
+        let j_chk = (*j as i64 + d_col).rem_euclid(j_size) as usize;
+        if let Cell::Alive(colour, _) = map[i_chk][j_chk] {
+            parents[colour as usize] += weight;
+        }
+    }
 
-    // [i-1,j+1] : NE
-    i_chk = i - 1;
-    j_chk = j + 1;
-    if i_chk < 0 {
-        i_chk = i_last;
+    let most = (1..=colours).map(|colour| parents[colour as usize]).max().unwrap_or(0);
+    if most == 0 {
+        return 0;
     }
-    if j_chk > j_last {
-        j_chk = 0;
+    let tied: Vec<u8> = (1..=colours).filter(|colour| parents[*colour as usize] == most).collect();
+    if colours == 4
+        && most == 1
+        && tied.len() == 3
+        && let Some(missing) = (1..=colours).find(|colour| parents[*colour as usize] == 0)
+    {
+        return missing;
     }
-    i_chk_ptr = i_chk as usize;
-    j_chk_ptr = j_chk as usize;
-    neighbors = match map[i_chk_ptr][j_chk_ptr] {
-        Cell::Alive(_) => neighbors + 1,
-        Cell::Dead(_) => neighbors,
-    };
-
-    // [_i_,j-1] : W
-    i_chk = i;
-    j_chk = j - 1;
-    if j_chk < 0 {
-        j_chk = j_last;
-    }
-    i_chk_ptr = i_chk as usize;
-    j_chk_ptr = j_chk as usize;
-    neighbors = match map[i_chk_ptr][j_chk_ptr] {
-        Cell::Alive(_) => neighbors + 1,
-        Cell::Dead(_) => neighbors,
-    };
-
-    // [_i_,j+1] : E
-    i_chk = i;
-    j_chk = j + 1;
-    if j_chk > j_last {
-        j_chk = 0;
-    }
-    i_chk_ptr = i_chk as usize;
-    j_chk_ptr = j_chk as usize;
-    neighbors = match map[i_chk_ptr][j_chk_ptr] {
-        Cell::Alive(_) => neighbors + 1,
-        Cell::Dead(_) => neighbors,
-    };
+    tied[(i + j) % tied.len()]
+}
 
-    // [i+1,j-1] : SW
-    i_chk = i + 1;
-    j_chk = j - 1;
-    if i_chk > i_last {
-        i_chk = 0;
+// Rule tables give the next state from the states of the cell and its
+// neighbours.
+fn calculate_table_state(map: &Vectrix, table: &RuleTable, rule: &Rule, lookup: &mut Lookup, i: &usize, j: &usize) -> Cell {
+    let i_size = map.len() as i64;
+    let j_size = map[0].len() as i64;
+
+    lookup.inputs.clear();
+    lookup.inputs.push(map[*i][*j].state());
+    for (d_row, d_col) in table.offsets() {
+        let i_chk = (*i as i64 + d_row).rem_euclid(i_size) as usize;
+        let j_chk = (*j as i64 + d_col).rem_euclid(j_size) as usize;
+        lookup.inputs.push(map[i_chk][j_chk].state());
     }
-    if j_chk < 0 {
-        j_chk = j_last;
-    }
-    i_chk_ptr = i_chk as usize;
-    j_chk_ptr = j_chk as usize;
-    neighbors = match map[i_chk_ptr][j_chk_ptr] {
-        Cell::Alive(_) => neighbors + 1,
-        Cell::Dead(_) => neighbors,
-    };
 
-    // [i+1,_j_] : S
-    i_chk = i + 1;
-    j_chk = j as i32;
-    if i_chk > i_last {
-        i_chk = 0;
-    }
-    i_chk_ptr = i_chk as usize;
-    j_chk_ptr = j_chk as usize;
-    neighbors = match map[i_chk_ptr][j_chk_ptr] {
-        Cell::Alive(_) => neighbors + 1,
-        Cell::Dead(_) => neighbors,
-    };
+    Cell::for_rule(table.next_state(lookup), rule)
+}
 
-    // [i+1,j+1] : SE
-    i_chk = i + 1;
-    j_chk = j + 1;
-    if i_chk > i_last {
-        i_chk = 0;
-    }
-    if j_chk > j_last {
-        j_chk = 0;
+// Which of the 8 cells around [i][j] are alive, one bit each in the order
+// of `rule::RING`. Non-totalistic rules look this up instead of a count.
+fn calculate_configuration(map: &Vectrix, i: &usize, j: &usize) -> u32 {
+    let i_size = map.len() as i64;
+    let j_size = map[0].len() as i64;
+
+    let mut configuration = 0;
+    for (bit, (d_row, d_col)) in RING.iter().enumerate() {
+        let i_chk = (*i as i64 + d_row).rem_euclid(i_size) as usize;
+        let j_chk = (*j as i64 + d_col).rem_euclid(j_size) as usize;
+        if let Cell::Alive(..) = map[i_chk][j_chk] {
+            configuration |= 1 << bit;
+        }
     }
-    i_chk_ptr = i_chk as usize;
-    j_chk_ptr = j_chk as usize;
-    neighbors = match map[i_chk_ptr][j_chk_ptr] {
-        Cell::Alive(_) => neighbors + 1,
-        Cell::Dead(_) => neighbors,
-    };
-
-    neighbors
+    configuration
 }
 
 fn delay(millis: u32) {
     std::thread::sleep(std::time::Duration::from_millis(millis as u64));
 }
 
-fn load_map(filename: &str) -> (Vectrix, String) {
+// The rule is returned too if the map has one.
+fn load_map(filename: &str) -> (Vectrix, Option<Rule>, String) {
     use std::fs::read_to_string;
     
     // Return default map of 2x2 if failed to read file.
     let content = match read_to_string(filename) {
         Ok(content) => content,
-        Err(_) => return (vec![vec![Cell::dead(); 2]; 2], format!("[-] Failed to load map.")),
+        Err(_) => return (vec![vec![Cell::dead(); 2]; 2], None, String::from("[-] Failed to load map.")),
     };
+    parse_map(&content)
+}
+
+// The map.txt format, from a file. A 2x2 map if it's no good.
+fn parse_map(content: &str) -> (Vectrix, Option<Rule>, String) {
+    match read_map(content) {
+        Ok((map, rule)) => {
+            let message = format!("[+] Map was loaded. Rule = {}", rule.describe());
+            (map, Some(rule), message)
+        },
+        Err(error) => (vec![vec![Cell::dead(); 2]; 2], None, format!("[-] Failed to load map: {error}.")),
+    }
+}
+
+// The map.txt format, from a file or the network. The error says why the
+// map can't be used, e.g. a rule this computer doesn't have.
+fn read_map(content: &str) -> Result<(Vectrix, Rule), String> {
+    // Maps of rules other than Life start with a `#R <rule>` line. Maps
+    // without one are Life maps.
+    let mut rule = Rule::life();
+    let mut content = content;
+    if let Some(header) = content.strip_prefix("#R ") {
+        let (rule_text, rest) = header.split_once("\r\n").unwrap_or((header, ""));
+        rule = Rule::parse(rule_text)?;
+        content = rest;
+    }
     
     let content = content.chars();
+
+    // Rule tables colour their states. States the rule doesn't have become
+    // its last state, e.g. [9] in a map of a 3-state rule.
+    let cell_of = |state: u8| Cell::for_rule(state.min(rule.states - 1), &rule);
     
-    // A unit is [x] or [ ]
+    // A unit is [x] or [ ], or a dying state like [2]
     let mut unit: String = String::new();
     
     // Init map with one row
@@ -663,7 +2452,7 @@ fn load_map(filename: &str) -> (Vectrix, String) {
         
         // If unit is an alive cell, push True
         if unit.eq("[x]") {
-            map[i].push(Cell::alive());
+            map[i].push(cell_of(1));
             unit.clear();
         };
 
@@ -673,6 +2462,19 @@ fn load_map(filename: &str) -> (Vectrix, String) {
             unit.clear();
         };
 
+        // If unit is a coloured cell, like [r], push its colour
+        if let Some(colour) = CELL_COLOURS.iter().position(|(_, letter, _)| unit == format!("[{letter}]")) {
+            map[i].push(Cell::coloured(colour as u8 + 1));
+            unit.clear();
+        };
+
+        // If unit is a dying cell, push its state
+        if unit.len() > 2 && unit.starts_with('[') && unit.ends_with(']') {
+            let state = unit[1..unit.len() - 1].parse::<u8>().unwrap_or(0);
+            map[i].push(cell_of(state));
+            unit.clear();
+        };
+
         // If new line, push Row
         if unit.eq("\r\n") {
             map.push(vec![]);
@@ -681,14 +2483,33 @@ fn load_map(filename: &str) -> (Vectrix, String) {
         }
     };
 
-    (map, format!("[+] Map was loaded."))
+    // A new line at the end gives an empty row, it isn't part of the map.
+    map.retain(|row| !row.is_empty());
+    if map.is_empty() {
+        return Err(String::from("there are no cells"));
+    }
+    rule.fits(map.len())?;
+    Ok((map, rule))
 }
 
-fn save_map(filename: &str, map: &Vectrix, ) -> String {
+fn save_map(filename: &str, map: &Vectrix, rule: &Rule) -> String {
     use std::fs::write;
 
+    match write(filename, map_text(map, rule).into_bytes()) {
+        Ok(_) => String::from("[+] Map saved."),
+        Err(_) => String::from("[-] Failed to save map."),
+    }
+}
+
+// The map in the map.txt format.
+fn map_text(map: &Vectrix, rule: &Rule) -> String {
     let mut content = String::new();
 
+    // Life maps are saved as they always were.
+    if !rule.is_life() {
+        content.push_str(&format!("#R {}\r\n", rule.name));
+    }
+
     // The peekable() method in the iterator allows
     // us to look ahead into the collection.
     // I decided to take this approach so I could look
@@ -700,10 +2521,14 @@ fn save_map(filename: &str, map: &Vectrix, ) -> String {
     while let Some(row) = iter.next() {
         for cell in row {
             let str_to_push = match cell {
-                Cell::Alive(_) => "[x]",
-                Cell::Dead(_) => "[ ]",
+                Cell::Alive(0, _) => String::from("[x]"),
+                // Coloured cells keep their colour, e.g. [r]
+                Cell::Alive(colour, _) => format!("[{}]", CELL_COLOURS[*colour as usize - 1].1),
+                Cell::Dead(_) => String::from("[ ]"),
+                // Dying cells keep their state, e.g. [2]
+                Cell::Dying(state, _) => format!("[{state}]"),
             };
-            content.push_str(str_to_push);                
+            content.push_str(&str_to_push);                
         }
         // If there is a another row next...
         if iter.peek().is_some() {
@@ -711,186 +2536,155 @@ fn save_map(filename: &str, map: &Vectrix, ) -> String {
             content.push_str("\r\n");
         }
     }
+    content
+}
 
-    match write(filename, content.into_bytes()) {
-        Ok(_) => return format!("[+] Map saved."),
-        Err(_) => return format!("[-] Failed to save map."),
-    };
+enum PlayEvent {
+    Continue,
+    Abort,
+    Resized,
 }
 
-fn esc_key_pressed() -> bool {
+fn poll_play_events() -> PlayEvent {
     use crossterm::event::{self, Event, KeyCode};
 
+    let mut play_event = PlayEvent::Continue;
+
     // poll(0) means it returns immediately with event availability information
     // using while instead of if to clear the pending events are processed (if many keys are pressed between ticks).
     while event::poll(std::time::Duration::from_millis(0)).unwrap() {
         match event::read().unwrap() {
-            Event::Key(key_event) => {
-                if key_event.code == KeyCode::Esc {
-                    return true;
-                }
+            Event::Key(key_event) if key_event.code == KeyCode::Esc => {
+                return PlayEvent::Abort;
+            },
+            Event::Resize(_, _) => {
+                play_event = PlayEvent::Resized;
             },
             _ => (),
         };
     }
-    false
+    play_event
 }
 
-fn set_infinite_game(prev_state: &bool) -> (bool, String) {
-    
+fn set_stop_when_stable(prev_state: &bool) -> (bool, String) {
     let new_state: bool = !prev_state;
 
     let message: String = match new_state {
-        true => format!("Infinite game Enabled"),
-        false => format!("Infinite game Disabled"),
+        true => String::from("Stop when stable Enabled"),
+        false => String::from("Stop when stable Disabled"),
     };
-    
-    return (new_state, message)
-}
 
-#[derive(Clone)]
-struct Coordinates {
-    row: usize,
-    col: usize,
+    (new_state, message)
 }
 
-impl Coordinates {
-    fn new(row: usize, col: usize) -> Coordinates {
-        Coordinates {
-            row,
-            col,
-        }
-    }
-    // more variants 
-    // .
-    // .
-       
-}
+fn set_stop_on_cycle(prev_state: &bool) -> (bool, String) {
+    let new_state: bool = !prev_state;
 
-enum Spaceship {
-    LightweightSpaceship(Vec<Coordinates>),
-    Glider(Vec<Coordinates>),
-}
+    let message: String = match new_state {
+        true => String::from("Stop on cycle Enabled"),
+        false => String::from("Stop on cycle Disabled"),
+    };
 
-impl Spaceship {
-    fn glider(origin: Coordinates) -> Spaceship {
-        let mut points = Vec::<Coordinates>::new();    
+    (new_state, message)
+}
 
-        let cell_1 = Coordinates::new(origin.row, origin.col);
-        let cell_2 = Coordinates::new(origin.row + 1, origin.col);
-        let cell_3 = Coordinates::new(origin.row + 1, origin.col + 2);
-        let cell_4 = Coordinates::new(origin.row + 2, origin.col);
-        let cell_5 = Coordinates::new(origin.row + 2, origin.col + 1);
-        
-        points.push(cell_1);
-        points.push(cell_2);
-        points.push(cell_3);
-        points.push(cell_4);
-        points.push(cell_5);
-        
-        Spaceship::Glider(points)
-    }
-    fn lightweight_spaceship(origin: Coordinates) -> Spaceship {
-        let mut points = Vec::<Coordinates>::new();    
-
-        let cell_1 = Coordinates::new(origin.row, origin.col + 1);
-        let cell_2 = Coordinates::new(origin.row, origin.col + 2);
-        let cell_3 = Coordinates::new(origin.row, origin.col + 4);
-        let cell_4 = Coordinates::new(origin.row + 1, origin.col);
-        let cell_5 = Coordinates::new(origin.row + 1, origin.col + 4);
-        let cell_6 = Coordinates::new(origin.row + 2, origin.col + 4);
-        let cell_7 = Coordinates::new(origin.row + 3, origin.col);
-        let cell_8 = Coordinates::new(origin.row + 3, origin.col + 3);
-        
-        points.push(cell_1);
-        points.push(cell_2);
-        points.push(cell_3);
-        points.push(cell_4);
-        points.push(cell_5);
-        points.push(cell_6);
-        points.push(cell_7);
-        points.push(cell_8);
-        
-        Spaceship::LightweightSpaceship(points)
+fn set_rule(prev_rule: &Rule, rows: usize) -> (Rule, String) {
+    let input = get_input(&String::from("Rule, e.g. B3/S23, 23/3, B2/S/C3, 345/2/4, B2/S34H, B2-a/S12, B3/S23/N111,101,111, QuadLife, WireWorld, Langtons-Loops or a .rule file (empty to keep):"));
+    if input.trim().is_empty() {
+        return (prev_rule.clone(), format!("Rule = {}", prev_rule.describe()));
     }
 
-    fn get_points(pattern: Spaceship) -> Vec<Coordinates> {
-        match pattern {
-            Spaceship::Glider(points) => {
-                points
-            },
-            Spaceship::LightweightSpaceship(points) => {
-                points
+    match Rule::parse(&input) {
+        Ok(rule) => match rule.fits(rows) {
+            Ok(()) => {
+                let message = format!("[+] Rule = {}", rule.describe());
+                (rule, message)
             },
+            Err(error) => (prev_rule.clone(), format!("[-] Can't use {}: {error}.", rule.describe())),
+        },
+        Err(error) => (prev_rule.clone(), format!("[-] Bad rule: {error}.")),
+    }
+}
+
+// Dying states the rule doesn't have are cleared, and colours it doesn't have
+// are taken off.
+fn fit_to_rule(map: &mut Vectrix, rule: &Rule) {
+    for cell in map.iter_mut().flatten() {
+        if cell.state() >= rule.states {
+            *cell = Cell::dead();
+        } else if cell.colour() > rule.colours {
+            *cell = Cell::alive();
         }
     }
 }
 
-enum StillLife {
-    FifteenBentPaperclip(Vec<Coordinates>),
-    // more variants 
-    // .
-    // .
+// Opens or closes the port spectators connect to.
+fn set_spectators(spectators: &mut Option<Spectators>) -> String {
+    if let Some(mut open) = spectators.take() {
+        open.close();
+        return String::from("Spectators Disabled");
+    }
+
+    let input = get_input(&format!("Port (empty for {}):", net::SPECTATOR_PORT));
+    let port = input.trim().parse::<u16>().unwrap_or(net::SPECTATOR_PORT);
+    match Spectators::open(port) {
+        Ok(open) => {
+            *spectators = Some(open);
+            format!("[+] Spectators Enabled, they can watch on port {port}")
+        },
+        Err(error) => format!("[-] No spectators: {error}."),
+    }
 }
 
-impl StillLife {
-    fn fifteen_bent_paperclip(origin: Coordinates) -> StillLife {
-        let mut coordinates = Vec::<Coordinates>::new();
+fn set_show_statistics(prev_state: &bool) -> (bool, String) {
+    let new_state: bool = !prev_state;
 
-        let cell_1 = Coordinates::new(origin.row, origin.col + 2);
-        let cell_2 = Coordinates::new(origin.row, origin.col + 3);
-        
-        let cell_3 = Coordinates::new(origin.row + 1, origin.col + 1);
-        let cell_4 = Coordinates::new(origin.row + 1, origin.col + 3);
-        let cell_5 = Coordinates::new(origin.row + 1, origin.col + 4);
-        let cell_6 = Coordinates::new(origin.row + 1, origin.col + 5);
-        
-        let cell_7 = Coordinates::new(origin.row + 2, origin.col);
-        let cell_8 = Coordinates::new(origin.row + 2, origin.col + 6);
-        
-        let cell_9 = Coordinates::new(origin.row + 3, origin.col + 1);
-        let cell_10 = Coordinates::new(origin.row + 3, origin.col + 2);
-        let cell_11 = Coordinates::new(origin.row + 3, origin.col + 3);
-        let cell_12 = Coordinates::new(origin.row + 3, origin.col + 4);
-        let cell_13 = Coordinates::new(origin.row + 3, origin.col + 6);
-
-        let cell_14 = Coordinates::new(origin.row + 4, origin.col + 3);
-        let cell_15 = Coordinates::new(origin.row + 4, origin.col + 5);
-
-        coordinates.push(cell_1);
-        coordinates.push(cell_2);
-
-        coordinates.push(cell_3);
-        coordinates.push(cell_4);
-        coordinates.push(cell_5);
-        coordinates.push(cell_6);
-        
-        coordinates.push(cell_7);
-        coordinates.push(cell_8);
+    let message: String = match new_state {
+        true => String::from("Statistics panel Enabled"),
+        false => String::from("Statistics panel Disabled"),
+    };
 
-        coordinates.push(cell_9);
-        coordinates.push(cell_10);
-        coordinates.push(cell_11);
-        coordinates.push(cell_12);
-        coordinates.push(cell_13);
+    (new_state, message)
+}
 
-        coordinates.push(cell_14);
-        coordinates.push(cell_15);
+fn set_cycle_window() -> (usize, String) {
+    let window = get_u32(&String::from("Cycle window (generations): ")).max(1) as usize;
+    (
+        window,
+        format!("Cycle window = {window} generations")
+    )
+}
 
-        StillLife::FifteenBentPaperclip(coordinates)
-    }
+fn set_infinite_game(prev_state: &bool) -> (bool, String) {
+    
+    let new_state: bool = !prev_state;
 
-    fn get_points(pattern: StillLife) -> Vec<Coordinates> {
-        match pattern {
-            StillLife::FifteenBentPaperclip(points) => {
-                points
-            },
-            // extra variants,
-        }
-    }
+    let message: String = match new_state {
+        true => String::from("Infinite game Enabled"),
+        false => String::from("Infinite game Disabled"),
+    };
+    
+    (new_state, message)
 }
 
+#[derive(Clone)]
+struct Coordinates {
+    row: usize,
+    col: usize,
+}
 
+impl Coordinates {
+    fn new(row: usize, col: usize) -> Coordinates {
+        Coordinates {
+            row,
+            col,
+        }
+    }
+    // more variants 
+    // .
+    // .
+       
+}
 
 struct Map;
 
@@ -899,6 +2693,7 @@ impl Map {
 
     // }
 
+    #[allow(clippy::new_ret_no_self)]
     fn new() -> (Vectrix, String) {
         print_message(&String::from("Generate your map."), true);
         
@@ -909,33 +2704,53 @@ impl Map {
         if rows == 0 || cols == 0 {
             return (
                 vec![vec![Cell::dead(); 10]; 10], 
-                String::from(format!("[-] Invalid dimensions. 10 x 10 map created."))
+                String::from("[-] Invalid dimensions. 10 x 10 map created.")
             );
         }
 
         (
             vec![vec![Cell::dead(); cols]; rows], 
-            String::from(format!("[+] {rows} x {cols} map created."))
+            format!("[+] {rows} x {cols} map created.")
         )
     }
 
-    fn set_clear_cell(map: &mut Vectrix) -> String {
+    fn set_clear_cell(map: &mut Vectrix, history: &mut History, rule: &mut Rule) -> String {
         let mut message = String::from("Set/Clear Cells");
         let message_loc = String::from("Enter Row and Column");
         // Default is "not edited"
         let mut edited: bool = false;
 
+        // Immigration and QuadLife: the colour of the cells that are set.
+        let mut colour = match rule.colours {
+            0 => 0,
+            colours => {
+                let prompt = format!("Colour of new cells (0 none, {}):", colour_names(colours));
+                get_u32(&prompt).min(colours as u32) as u8
+            },
+        };
+
         loop {
             clear_console();
             print_header(vec!["Game of Life"]);
-            print_map(&map, true, true);
+            print_map(map, true, true);
             print_message(&message, true);
             print_message(&message_loc, true);
             
-            let (row, aborted)  = get_usize(&String::from("Row:"), true);
-            if aborted {
-                break;
-            }
+            let row = match get_edit_usize("Row:") {
+                EditInput::Value(row) => row,
+                // Undo can bring back a rule with fewer colours.
+                EditInput::Undo => {
+                    message = history.undo(map, rule);
+                    colour = colour.min(rule.colours);
+                    continue;
+                },
+                EditInput::Redo => {
+                    message = history.redo(map, rule);
+                    colour = colour.min(rule.colours);
+                    continue;
+                },
+                EditInput::Quit => break,
+            };
             
             let (col, aborted) = get_usize(&String::from("Col:"), true);
             if aborted {
@@ -948,13 +2763,18 @@ impl Map {
             let filtered_row = row % row_len;
             let filtered_col = col % col_len;
             
-            map[filtered_row][filtered_col] = map[filtered_row][filtered_col].not();
+            let before = map.clone();
+            map[filtered_row][filtered_col] = match map[filtered_row][filtered_col].not() {
+                Cell::Alive(..) if colour > 0 => Cell::coloured(colour),
+                cell => cell,
+            };
+            history.record("Set/Clear cell", &before, map);
             
             match &map[filtered_row][filtered_col] {
-                Cell::Alive(ch) => { 
+                Cell::Alive(_, ch) => { 
                     message = format!("[{ch} ] Alive cell at [{filtered_row:>2}][{filtered_row:>2}]");
                 },
-                Cell::Dead(ch) => { 
+                Cell::Dead(ch) | Cell::Dying(_, ch) => { 
                     message = format!("[{ch} ] Dead cell at [{filtered_row:>2}][{filtered_col:>2}]");
                 },
             };
@@ -969,23 +2789,113 @@ impl Map {
             message = String::from("Aborted");
         }
 
-        return message;
+        message
     }
 
-    fn generate_spaceship(map: &mut Vectrix, pattern: Spaceship) -> String {
-        let message: String;
+    fn draw(map: &mut Vectrix, history: &mut History, rule: &mut Rule) -> String {
+        let mut message = String::from("Drawing tools. Pen draws alive cells.");
+        // State the pen draws, 1 (alive) or 0 (dead). Rules with more states
+        // can pick any of them.
+        let mut pen: u8 = 1;
 
-        let points = match pattern {
-            Spaceship::Glider(points) => {
-                message = String::from("[+] Glider created.");
-                points
-            },
-            Spaceship::LightweightSpaceship(points) => {
-                message = String::from("[+] Lightweight Spaceship created.");
-                points
-            },
-        };
+        loop {
+            clear_console();
+            print_header(vec!["Game of Life"]);
+            print_map(map, true, true);
+            print_message(&message, true);
+
+            let menu_opt = draw_menu();
+
+            let (label, points, cell) = match menu_opt {
+                DrawMenuOpt::Line
+                | DrawMenuOpt::Rectangle
+                | DrawMenuOpt::FilledRectangle
+                | DrawMenuOpt::Ellipse
+                | DrawMenuOpt::FilledEllipse => {
+                    let from = match get_coordinates("From") {
+                        Some(from) => from,
+                        None => continue,
+                    };
+                    let to = match get_coordinates("To") {
+                        Some(to) => to,
+                        None => continue,
+                    };
+                    let (label, points) = match menu_opt {
+                        DrawMenuOpt::Line => ("Line", draw::line(&from, &to)),
+                        DrawMenuOpt::Rectangle => ("Rectangle", draw::rectangle(&from, &to, false)),
+                        DrawMenuOpt::FilledRectangle => ("Filled rectangle", draw::rectangle(&from, &to, true)),
+                        DrawMenuOpt::Ellipse => ("Ellipse", draw::ellipse(&from, &to, false)),
+                        _ => ("Filled ellipse", draw::ellipse(&from, &to, true)),
+                    };
+                    let cell = match (pen, rule.colours) {
+                        (0, _) | (_, 0) => Cell::for_rule(pen, rule),
+                        (colour, _) => Cell::coloured(colour),
+                    };
+                    (label, points, cell)
+                },
+                DrawMenuOpt::FloodFill => {
+                    let seed = match get_coordinates("Seed") {
+                        Some(seed) => seed,
+                        None => continue,
+                    };
+                    // The region flips to the opposite state.
+                    let cell = map[seed.row % map.len()][seed.col % map[0].len()].not();
+                    ("Flood fill", draw::flood_fill(map, &seed), cell)
+                },
+                DrawMenuOpt::Pen => {
+                    pen = match (&rule.table, rule.colours) {
+                        (Some(_), _) => {
+                            let prompt = format!("Pen state (0 to {}):", rule.states - 1);
+                            get_u32(&prompt).min(rule.states as u32 - 1) as u8
+                        },
+                        (None, 0) => 1 - pen.min(1),
+                        (None, colours) => {
+                            let prompt = format!("Pen colour (0 dead, {}):", colour_names(colours));
+                            get_u32(&prompt).min(colours as u32) as u8
+                        },
+                    };
+                    message = match (pen, rule.colours) {
+                        (0, _) => String::from("Pen draws dead cells."),
+                        (colour, colours) if colours > 0 => format!("Pen draws {} cells.", CELL_COLOURS[colour as usize - 1].0),
+                        (1, _) => String::from("Pen draws alive cells."),
+                        (state, _) => format!("Pen draws state {state}."),
+                    };
+                    continue;
+                },
+                // Undo can bring back a rule with fewer states or colours.
+                DrawMenuOpt::Undo => {
+                    message = history.undo(map, rule);
+                    pen = pen.min(rule.colours.max(rule.states - 1));
+                    continue;
+                },
+                DrawMenuOpt::Redo => {
+                    message = history.redo(map, rule);
+                    pen = pen.min(rule.colours.max(rule.states - 1));
+                    continue;
+                },
+                DrawMenuOpt::Exit => break,
+                DrawMenuOpt::Unknown => continue,
+            };
 
+            let before = map.clone();
+            draw::apply(map, &points, &cell);
+            history.record(label, &before, map);
+            message = format!("[+] {label} drawn ({} cells).", points.len());
+        }
+
+        String::from("Drawing finished.")
+    }
+
+    fn clear(map: &mut Vectrix) -> String {
+        for row in map.iter_mut() {
+            for cell in row.iter_mut() {
+                *cell = Cell::dead();
+            }
+        }
+        String::from("[+] Map cleared.")
+    }
+
+    fn generate_pattern(map: &mut Vectrix, points: Vec<Coordinates>) -> String {
         for point in points {
             let row_len = map.len();
             let col_len = map[0].len();
@@ -996,63 +2906,262 @@ impl Map {
             map[filtered_row][filtered_col] = Cell::alive();
         }
 
-        message
+        String::from("[+] Pattern generated.")
     }
+}
 
-    fn generate_still_life(map: &mut Vectrix, pattern: StillLife) -> String {
-        let message: String;
+// fn print_rules() {
 
-        let points = match pattern {
-            StillLife::FifteenBentPaperclip(points) => {
-                message = String::from("[+] Fifteen Bent Paperclip created.");
-                points
-            },
-        };
+// }
 
-        for point in points {
-            let row_len = map.len();
-            let col_len = map[0].len();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    // A host on `port` and a peer that joined it.
+    fn session_pair(port: u16) -> (Session, Session) {
+        let mut host = Session::host(port).unwrap();
+        // Joining waits for WELCOME, which the host only sends when it polls.
+        let joining = std::thread::spawn(move || Session::join("127.0.0.1", port, "tester"));
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !host.poll().iter().any(|event| matches!(event, SessionEvent::Joined(..))) {
+            assert!(Instant::now() < deadline, "nobody joined");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        (host, joining.join().unwrap().unwrap())
+    }
 
-            let filtered_row = point.row % row_len;
-            let filtered_col = point.col % col_len;
+    // The next message `session` gets.
+    fn next_message(session: &mut Session) -> Message {
+        next_messages(session, 1).remove(0)
+    }
 
-            map[filtered_row][filtered_col] = Cell::alive();
+    // The next `count` messages `session` gets, in order.
+    fn next_messages(session: &mut Session, count: usize) -> Vec<Message> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut messages = Vec::new();
+        while messages.len() < count {
+            for event in session.poll() {
+                if let SessionEvent::Received(_, message) = event {
+                    messages.push(message);
+                }
+            }
+            assert!(Instant::now() < deadline, "no message came");
+            std::thread::sleep(Duration::from_millis(10));
         }
+        messages
+    }
 
-        message
+    fn blinker() -> Vectrix {
+        let mut map: Vectrix = vec![vec![Cell::dead(); 6]; 6];
+        map[2][1..4].fill(Cell::alive());
+        map
     }
 
-    fn generate_pattern(map: &mut Vectrix, points: Vec<Coordinates>) -> String {
-        for point in points {
-            let row_len = map.len();
-            let col_len = map[0].len();
+    #[test]
+    fn peers_follow_ticks_and_hashes() {
+        let (mut host, mut peer) = session_pair(47_901);
+        let mut game_properties = GameConfig::get_config();
+        let mut map = blinker();
+        let mut lockstep = Lockstep::new();
 
-            let filtered_row = point.row % row_len;
-            let filtered_col = point.col % col_len;
+        let tick = Message::Tick { generation: 0, generations: 3 };
+        assert!(peer_input(&mut map, &mut game_properties, &mut peer, &mut lockstep, tick).is_some());
+        assert_eq!(lockstep.generation, 3);
+        assert!(map == next_generation(&blinker(), &game_properties.rule));
 
-            map[filtered_row][filtered_col] = Cell::alive();
-        }
+        let hash = Message::Hash { generation: 3, hash: net::state_hash(&map, &game_properties.rule) };
+        peer_input(&mut map, &mut game_properties, &mut peer, &mut lockstep, hash);
+        assert!(!lockstep.resyncing);
 
-        String::from("[+] Pattern generated.")
+        host.close();
+        peer.close();
     }
-}
 
-// enum StillLifes {
-//     //
-// }
+    #[test]
+    fn wrong_hash_resyncs_from_the_host() {
+        let (mut host, mut peer) = session_pair(47_902);
+        let mut host_properties = GameConfig::get_config();
+        let mut host_map = blinker();
+        let mut host_lockstep = Lockstep::new();
+        host_lockstep.generation = 8;
+
+        // The peer missed an edit somewhere.
+        let mut game_properties = GameConfig::get_config();
+        let mut map = blinker();
+        map[0][0] = Cell::alive();
+        let mut lockstep = Lockstep::new();
+        lockstep.generation = 8;
+
+        let hash = Message::Hash { generation: 8, hash: net::state_hash(&host_map, &host_properties.rule) };
+        peer_input(&mut map, &mut game_properties, &mut peer, &mut lockstep, hash);
+        assert!(lockstep.resyncing);
+
+        // Inputs are skipped until the map comes.
+        let edit = Message::Edit { generation: 8, row: 5, col: 5, state: 1, colour: 0 };
+        assert!(peer_input(&mut map, &mut game_properties, &mut peer, &mut lockstep, edit).is_none());
+        assert!(matches!(map[5][5], Cell::Dead(_)));
+
+        let resync = next_message(&mut host);
+        assert_eq!(resync, Message::Resync);
+        host_input(&mut host_map, &mut host_properties, &mut host, &mut host_lockstep, 1, resync);
+        let snapshot = next_message(&mut peer);
+        assert!(matches!(snapshot, Message::Snapshot { generation: 8, .. }));
+        peer_input(&mut map, &mut game_properties, &mut peer, &mut lockstep, snapshot);
+        assert!(!lockstep.resyncing);
+        assert!(map == host_map);
+
+        host.close();
+        peer.close();
+    }
 
-// enum Oscillators {
-//     //
-// }
+    #[test]
+    fn inputs_for_another_generation_resync() {
+        let (mut host, mut peer) = session_pair(47_903);
+        let mut game_properties = GameConfig::get_config();
+        let mut map = blinker();
+        let mut lockstep = Lockstep::new();
+
+        let edit = Message::Edit { generation: 2, row: 0, col: 0, state: 1, colour: 0 };
+        peer_input(&mut map, &mut game_properties, &mut peer, &mut lockstep, edit);
+        assert!(lockstep.resyncing);
+        assert!(matches!(map[0][0], Cell::Dead(_)));
+        assert_eq!(next_message(&mut host), Message::Resync);
+
+        host.close();
+        peer.close();
+    }
 
-// enum Methuselahs {
-//     //
-// }
+    #[test]
+    fn full_generation_counts_start_again() {
+        let (mut host, mut peer) = session_pair(47_904);
+        let host_properties = GameConfig::get_config();
+        let mut host_map = blinker();
+        let mut host_lockstep = Lockstep::new();
+        host_lockstep.generation = u32::MAX - 1;
+        let mut game_properties = GameConfig::get_config();
+        let mut map = blinker();
+        let mut lockstep = Lockstep::new();
+        lockstep.generation = u32::MAX - 1;
+
+        step_session(&mut host_map, &host_properties, &mut host, &mut host_lockstep, 3);
+        assert_eq!(host_lockstep.generation, 0);
+
+        // TICK, then the map from 0, then the hash to check it against.
+        let [tick, snapshot, hash] = next_messages(&mut peer, 3).try_into().ok().unwrap();
+        peer_input(&mut map, &mut game_properties, &mut peer, &mut lockstep, tick);
+        assert!(lockstep.resyncing);
+        assert_eq!(next_message(&mut host), Message::Resync);
+        assert!(matches!(snapshot, Message::Snapshot { generation: 0, .. }));
+        peer_input(&mut map, &mut game_properties, &mut peer, &mut lockstep, snapshot);
+        peer_input(&mut map, &mut game_properties, &mut peer, &mut lockstep, hash);
+        assert!(!lockstep.resyncing);
+        assert!(map == host_map);
+
+        host.close();
+        peer.close();
+    }
 
-// enum Guns {
-//     //
-// }
+    #[test]
+    fn loaded_states_fit_the_rule() {
+        let (map, rule, _) = parse_map("#R B2/S/C3\r\n[255][x]\r\n[2][ ]");
+        let rule = rule.unwrap();
+        assert_eq!(map[0][0].state(), 2);
+        assert_eq!(map[1][0].state(), 2);
+
+        // The last state decays to dead instead of overflowing.
+        assert_eq!(map[0][0].decay(&rule).state(), 0);
+        assert_eq!(Cell::dying(255).decay(&rule).state(), 0);
+        let next = next_generation(&map, &rule);
+        assert!(next.iter().flatten().all(|cell| cell.state() < rule.states));
+    }
 
-// fn print_rules() {
+    #[test]
+    fn life_maps_have_two_states() {
+        let (map, rule, _) = parse_map("[5][x]\r\n[ ][ ]");
+        assert_eq!(rule.unwrap().name, "B3/S23");
+        assert_eq!(map[0][0].state(), 1);
+    }
+
+    #[test]
+    fn speeds_are_only_measured_under_life() {
+        let map = blinker();
+        assert_eq!(measure_speed(&map, None, &Rule::life()), "[+] Oscillator of period 2.");
+        let message = measure_speed(&map, None, &Rule::parse("B36/S23").unwrap());
+        assert!(message.starts_with("[-]"), "{message}");
+    }
+
+    #[test]
+    fn half_blocks_show_every_live_state() {
+        let (map, _, _) = parse_map("#R B2/S/C3\r\n[x][2][ ]\r\n[ ][2][x]");
+        let viewport = Viewport { top: 0, left: 0, rows: 2, cols: 3, density: Density::HalfBlock, hex: false };
+        assert_eq!(render_map(&map, &viewport, false, false), ["▀█▄"]);
+    }
+
+    #[test]
+    fn hexagonal_maps_have_even_rows() {
+        let (map, rule, message) = parse_map("#R B2/S34H\r\n[x][ ]\r\n[ ][ ]\r\n[ ][x]");
+        assert!(rule.is_none());
+        assert_eq!(map.len(), 2);
+        assert!(message.contains("even number of rows"));
+
+        // A new line at the end isn't a row.
+        let (map, rule, _) = parse_map("#R B2/S34H\r\n[x][ ]\r\n[ ][x]\r\n");
+        assert!(rule.is_some());
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn newborn_colours_use_weights_and_position() {
+        let mut map = vec![vec![Cell::dead(); 4]; 4];
+        map[0][0] = Cell::coloured(1);
+        map[2][0] = Cell::coloured(2);
+        map[1][1] = Cell::coloured(2);
+
+        // Red above weighs 3, more than two blue cells of weight 1.
+        let weighted = [(-1, 0, 3), (1, 0, 1), (0, 1, 1)];
+        assert_eq!(newborn_colour(&map, &weighted, 2, &1, &0), 1);
+
+        // One red and one blue: the position picks the winner.
+        let ring = [(-1, 0, 1), (1, 0, 1)];
+        assert_eq!(newborn_colour(&map, &ring, 2, &1, &0), 2);
+        map[1][1] = Cell::dead();
+        map[0][1] = Cell::coloured(1);
+        map[2][1] = Cell::coloured(2);
+        assert_eq!(newborn_colour(&map, &ring, 2, &1, &1), 1);
+    }
+
+    #[test]
+    fn received_maps_keep_their_rule() {
+        // A .rule file only the host has: no map, and no switch to Life.
+        let error = received_map("#R nonsense.rule\r\n[x][x][x]\r\n[ ][ ][ ]").err().unwrap();
+        assert!(error.contains("nonsense.rule"), "{error}");
+
+        let (map, rule) = received_map("#R B36/S23\r\n[x][x][x]\r\n[ ][ ][ ]\r\n").unwrap();
+        assert_eq!((map.len(), map[0].len()), (2, 3));
+        assert_eq!(rule.name, "B36/S23");
+        assert!(received_map("[x][x]\r\n[ ]").is_err());
+    }
 
-// }
\ No newline at end of file
+    #[test]
+    fn network_cells_fit_the_rule() {
+        let rule = Rule::parse("B2/S/C3").unwrap();
+        assert_eq!(network_cell(255, 0, &rule).state(), 2);
+        // Next generation doesn't overflow either.
+        assert_eq!(network_cell(255, 0, &rule).decay(&rule).state(), 0);
+        assert_eq!(network_cell(1, 3, &rule).colour(), 0);
+
+        let rule = Rule::parse("Immigration").unwrap();
+        assert_eq!(network_cell(1, 2, &rule).colour(), 2);
+        assert_eq!(network_cell(1, 4, &rule).colour(), 2);
+    }
+
+    #[test]
+    fn remote_rules_cant_read_files() {
+        assert!(Rule::parse_remote("/etc/secret.rule").is_err());
+        assert!(Rule::parse_remote("secret.rule Immigration").is_err());
+        assert_eq!(Rule::parse_remote("WireWorld").unwrap().states, 4);
+        assert!(Rule::parse_remote("B36/S23").is_ok());
+    }
+}
@@ -0,0 +1,683 @@
+// Network sessions: several computers sharing one universe.
+//
+// One computer hosts, the others join it over TCP. Everything goes through
+// the host: edits, rule changes and steps are sent to it, it applies them and
+// passes them on to everybody in the same order (lockstep). Only these inputs
+// go over the network, every computer runs the generations itself.
+//
+// Inputs carry the generation they happen at. Now and then the host also sends
+// a hash of its map. A peer that is at another generation, or has another
+// hash, is out of sync and asks for the whole map again.
+//
+// Spectators join the same way, on another port, but only watch: they get the
+// map when they join and when a game starts, then the cells that changed in
+// every generation.
+//
+// A message is a line of text, a keyword and its fields split by spaces.
+// Messages with a body (a map) end the line with the size of the body in
+// bytes, and the body follows the line.
+//
+//     HELLO 3 alice       join: protocol version and name
+//     WELCOME 3 3         accepted: protocol version and peer number
+//     REJECT reason       not accepted, the connection is closed
+//     SNAPSHOT 42 1234    generation 42, then 1234 bytes of map.txt
+//     EDIT 42 4 7 1 0     at generation 42 cell [4][7] is now state 1, colour 0
+//     RULE 42 B36/S23     at generation 42 the rule is now B36/S23
+//     STEP 10             peers: please run 10 generations
+//     TICK 42 10          host: run 10 generations from generation 42
+//     HASH 52 8817...     host: hash of the map at generation 52
+//     DELTA 43 24         generation 43, then 24 bytes of changed cells, one
+//                         `row col state colour` line each
+//     RESYNC              peers: send me the whole map
+//     BYE                 leaving
+//
+// The version goes up when the messages change. The host only takes peers
+// with the same version.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::{CELL_COLOURS, Rule, Vectrix};
+
+pub const PROTOCOL_VERSION: u32 = 3;
+pub const DEFAULT_PORT: u16 = 7878;
+pub const SPECTATOR_PORT: u16 = 7879;
+// How often the host sends a HASH when nothing else happens.
+pub const HASH_INTERVAL: Duration = Duration::from_secs(5);
+
+// Most generations one STEP runs, so nobody can keep everybody busy for
+// hours.
+pub const MAX_STEP: u32 = 10_000;
+
+// How long to wait for the host to answer HELLO.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+// A peer that takes longer than this to read a message is dropped, so it
+// can't hold up the others.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+// Bigger bodies are refused, so a bad peer can't eat all the memory.
+const MAX_BODY: usize = 64 * 1024 * 1024;
+
+// Row, column, state and colour of a cell.
+pub type CellChange = (usize, usize, u8, u8);
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Message {
+    Hello { version: u32, name: String },
+    Welcome { version: u32, peer: u32 },
+    Reject(String),
+    Snapshot { generation: u32, map: String },
+    Edit { generation: u32, row: usize, col: usize, state: u8, colour: u8 },
+    Rule { generation: u32, rule: String },
+    Step { generations: u32 },
+    Tick { generation: u32, generations: u32 },
+    Hash { generation: u32, hash: u64 },
+    Delta { generation: u32, cells: Vec<CellChange> },
+    Resync,
+    Bye,
+}
+
+impl Message {
+    pub fn encode(&self) -> Vec<u8> {
+        let line = match self {
+            Message::Hello { version, name } => format!("HELLO {version} {name}\n"),
+            Message::Welcome { version, peer } => format!("WELCOME {version} {peer}\n"),
+            Message::Reject(reason) => format!("REJECT {reason}\n"),
+            Message::Snapshot { generation, map } => format!("SNAPSHOT {generation} {}\n{map}", map.len()),
+            Message::Edit { generation, row, col, state, colour } => format!("EDIT {generation} {row} {col} {state} {colour}\n"),
+            Message::Rule { generation, rule } => format!("RULE {generation} {rule}\n"),
+            Message::Step { generations } => format!("STEP {generations}\n"),
+            Message::Tick { generation, generations } => format!("TICK {generation} {generations}\n"),
+            Message::Hash { generation, hash } => format!("HASH {generation} {hash}\n"),
+            Message::Delta { generation, cells } => {
+                let body: String = cells
+                    .iter()
+                    .map(|(row, col, state, colour)| format!("{row} {col} {state} {colour}\n"))
+                    .collect();
+                format!("DELTA {generation} {}\n{body}", body.len())
+            },
+            Message::Resync => String::from("RESYNC\n"),
+            Message::Bye => String::from("BYE\n"),
+        };
+        line.into_bytes()
+    }
+
+    // The next message, None when the other side closed the connection.
+    pub fn read(reader: &mut impl BufRead) -> Result<Option<Message>, String> {
+        let mut line = String::new();
+        match reader.read_line(&mut line) {
+            Ok(0) => return Ok(None),
+            Ok(_) => (),
+            Err(error) => return Err(error.to_string()),
+        }
+        let line = line.trim_end_matches(['\r', '\n']);
+        let (keyword, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let fields: Vec<&str> = rest.split_whitespace().collect();
+
+        // Field `n` as a number of the type it's going into. Numbers that
+        // don't fit, like a state of 300, are an error rather than cut down.
+        fn field<T: std::str::FromStr>(fields: &[&str], n: usize, line: &str) -> Result<T, String> {
+            fields
+                .get(n)
+                .and_then(|field| field.parse::<T>().ok())
+                .ok_or_else(|| format!("bad message `{line}`"))
+        }
+        // Colours go from 0, no colour, to the last of CELL_COLOURS.
+        fn colour(fields: &[&str], n: usize, line: &str) -> Result<u8, String> {
+            match field::<u8>(fields, n, line)? {
+                colour if colour as usize <= CELL_COLOURS.len() => Ok(colour),
+                _ => Err(format!("bad colour in `{line}`")),
+            }
+        }
+
+        let message = match keyword {
+            "HELLO" => Message::Hello {
+                version: field(&fields, 0, line)?,
+                name: fields[1..].join(" "),
+            },
+            "WELCOME" => Message::Welcome {
+                version: field(&fields, 0, line)?,
+                peer: field(&fields, 1, line)?,
+            },
+            "REJECT" => Message::Reject(rest.to_string()),
+            "SNAPSHOT" => Message::Snapshot {
+                generation: field(&fields, 0, line)?,
+                map: read_body(reader, field(&fields, 1, line)?)?,
+            },
+            "EDIT" => Message::Edit {
+                generation: field(&fields, 0, line)?,
+                row: field(&fields, 1, line)?,
+                col: field(&fields, 2, line)?,
+                state: field(&fields, 3, line)?,
+                colour: colour(&fields, 4, line)?,
+            },
+            "RULE" => Message::Rule {
+                generation: field(&fields, 0, line)?,
+                rule: fields.get(1..).unwrap_or_default().join(" "),
+            },
+            "STEP" => Message::Step {
+                generations: field(&fields, 0, line)?,
+            },
+            "TICK" => {
+                // Hosts never step further, see MAX_STEP.
+                let generations = field(&fields, 1, line)?;
+                if generations > MAX_STEP {
+                    return Err(format!("too many generations in `{line}`"));
+                }
+                Message::Tick {
+                    generation: field(&fields, 0, line)?,
+                    generations,
+                }
+            },
+            "HASH" => Message::Hash {
+                generation: field(&fields, 0, line)?,
+                hash: field(&fields, 1, line)?,
+            },
+            "DELTA" => {
+                let body = read_body(reader, field(&fields, 1, line)?)?;
+                let mut cells = Vec::<CellChange>::new();
+                for cell in body.lines() {
+                    let numbers: Vec<&str> = cell.split_whitespace().collect();
+                    if numbers.len() != 4 {
+                        return Err(format!("bad cell `{cell}`"));
+                    }
+                    cells.push((
+                        field(&numbers, 0, cell)?,
+                        field(&numbers, 1, cell)?,
+                        field(&numbers, 2, cell)?,
+                        colour(&numbers, 3, cell)?,
+                    ));
+                }
+                Message::Delta {
+                    generation: field(&fields, 0, line)?,
+                    cells,
+                }
+            },
+            "RESYNC" => Message::Resync,
+            "BYE" => Message::Bye,
+            _ => return Err(format!("unknown message `{line}`")),
+        };
+        Ok(Some(message))
+    }
+}
+
+// The `size` bytes of text after a message line.
+fn read_body(reader: &mut impl BufRead, size: usize) -> Result<String, String> {
+    if size > MAX_BODY {
+        return Err(format!("a body of {size} bytes is too big"));
+    }
+    let mut body = vec![0u8; size];
+    reader.read_exact(&mut body).map_err(|error| error.to_string())?;
+    String::from_utf8(body).map_err(|_| String::from("the body is not text"))
+}
+
+// The cells that are different in `after`, for a DELTA.
+pub fn changes(before: &Vectrix, after: &Vectrix) -> Vec<CellChange> {
+    let mut cells = Vec::<CellChange>::new();
+    for (i, (old_row, new_row)) in before.iter().zip(after).enumerate() {
+        for (j, (old, new)) in old_row.iter().zip(new_row).enumerate() {
+            if old != new {
+                cells.push((i, j, new.state(), new.colour()));
+            }
+        }
+    }
+    cells
+}
+
+// Where a computer is in the shared game.
+pub struct Lockstep {
+    pub generation: u32,
+    // Out of sync and waiting for the whole map. Inputs until then are skipped.
+    pub resyncing: bool,
+    last_hash: Instant,
+}
+
+impl Lockstep {
+    pub fn new() -> Lockstep {
+        Lockstep {
+            generation: 0,
+            resyncing: false,
+            last_hash: Instant::now(),
+        }
+    }
+
+    // Hosts: true every HASH_INTERVAL.
+    pub fn hash_due(&mut self) -> bool {
+        if self.last_hash.elapsed() < HASH_INTERVAL {
+            return false;
+        }
+        self.last_hash = Instant::now();
+        true
+    }
+
+    // Moves the count on by `generations`. False, and the count stays, when
+    // it would go past u32::MAX.
+    pub fn advance(&mut self, generations: u32) -> bool {
+        match self.generation.checked_add(generations) {
+            Some(generation) => {
+                self.generation = generation;
+                true
+            },
+            None => false,
+        }
+    }
+}
+
+// Same map and rule, same hash, on every computer. Written by hand (FNV-1a)
+// because Rust's own hasher may change between versions.
+pub fn state_hash(map: &Vectrix, rule: &Rule) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let bytes = map.iter().flatten().flat_map(|cell| [cell.state(), cell.colour()]);
+    for byte in rule.name.bytes().chain(bytes) {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+// What the reader threads tell the session.
+enum NetEvent {
+    // A new connection, and where to put what is sent to it.
+    Connected(u32, Sender<Vec<u8>>),
+    Received(u32, Message),
+    Disconnected(u32),
+}
+
+// What the session tells the game.
+pub enum SessionEvent {
+    Joined(u32, String),
+    Left(u32, String),
+    Received(u32, Message),
+    // The session is over, e.g. the host left. The reason is given.
+    Closed(String),
+}
+
+struct Peer {
+    id: u32,
+    name: String,
+    // Messages for its writer thread, see `spawn_writer`.
+    outbox: Sender<Vec<u8>>,
+    // Said HELLO with the right version.
+    joined: bool,
+}
+
+pub struct Session {
+    pub hosting: bool,
+    // 0 is the host.
+    pub peer_id: u32,
+    // Hosts: everybody that connected. Clients: only the host.
+    peers: Vec<Peer>,
+    events: Receiver<NetEvent>,
+    address: String,
+    // Tells the thread taking connections to stop, so the port is free again.
+    stopped: Arc<AtomicBool>,
+}
+
+impl Session {
+    pub fn host(port: u16) -> Result<Session, String> {
+        let listener = TcpListener::bind(("0.0.0.0", port)).map_err(|error| format!("can't listen on port {port}: {error}"))?;
+        // Not waiting in accept(), so it can look at `stopped` now and then.
+        listener.set_nonblocking(true).map_err(|error| error.to_string())?;
+        let (sender, events) = channel::<NetEvent>();
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        let stop = stopped.clone();
+        std::thread::spawn(move || {
+            // Peers are numbered from 1, the host is 0.
+            let mut next_id = 1;
+            while !stop.load(Ordering::Relaxed) {
+                let stream = match listener.accept() {
+                    Ok((stream, _)) => stream,
+                    Err(_) => {
+                        std::thread::sleep(Duration::from_millis(50));
+                        continue;
+                    },
+                };
+                stream.set_nonblocking(false).ok();
+                let Ok(writer) = stream.try_clone() else {
+                    continue;
+                };
+                if sender.send(NetEvent::Connected(next_id, spawn_writer(writer))).is_err() {
+                    break;
+                }
+                spawn_reader(next_id, BufReader::new(stream), sender.clone(), true);
+                next_id += 1;
+            }
+        });
+
+        Ok(Session {
+            hosting: true,
+            peer_id: 0,
+            peers: Vec::new(),
+            events,
+            address: format!("port {port}"),
+            stopped,
+        })
+    }
+
+    // `address` is host:port, without the port `port` is used.
+    pub fn join(address: &str, port: u16, name: &str) -> Result<Session, String> {
+        let address = match address.contains(':') {
+            true => address.to_string(),
+            false => format!("{address}:{port}"),
+        };
+        let stream = TcpStream::connect(&address).map_err(|error| format!("can't connect to {address}: {error}"))?;
+        let mut writer = stream.try_clone().map_err(|error| error.to_string())?;
+        let mut reader = BufReader::new(stream);
+
+        let hello = Message::Hello {
+            version: PROTOCOL_VERSION,
+            name: name.to_string(),
+        };
+        writer.write_all(&hello.encode()).map_err(|error| error.to_string())?;
+
+        // The answer comes before anything else.
+        reader.get_ref().set_read_timeout(Some(HANDSHAKE_TIMEOUT)).ok();
+        let peer_id = match Message::read(&mut reader) {
+            Ok(Some(Message::Welcome { peer, .. })) => peer,
+            Ok(Some(Message::Reject(reason))) => return Err(format!("the host said no: {reason}")),
+            Ok(_) => return Err(String::from("the host didn't answer")),
+            Err(error) => return Err(error),
+        };
+        reader.get_ref().set_read_timeout(None).ok();
+
+        let (sender, events) = channel::<NetEvent>();
+        spawn_reader(0, reader, sender, false);
+
+        Ok(Session {
+            hosting: false,
+            peer_id,
+            peers: vec![Peer {
+                id: 0,
+                name: String::from("host"),
+                outbox: spawn_writer(writer),
+                joined: true,
+            }],
+            events,
+            address,
+            stopped: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    // Everything that happened since the last call. Doesn't wait.
+    pub fn poll(&mut self) -> Vec<SessionEvent> {
+        let mut happened = Vec::<SessionEvent>::new();
+
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                NetEvent::Connected(id, outbox) => self.peers.push(Peer {
+                    id,
+                    name: format!("peer {id}"),
+                    outbox,
+                    joined: false,
+                }),
+                // Already welcomed by its reader, see `spawn_reader`.
+                NetEvent::Received(id, Message::Hello { name, .. }) if self.hosting => {
+                    if let Some(peer) = self.peers.iter_mut().find(|peer| peer.id == id) {
+                        peer.joined = true;
+                        peer.name = name.clone();
+                    }
+                    happened.push(SessionEvent::Joined(id, name));
+                },
+                NetEvent::Received(id, Message::Bye) | NetEvent::Disconnected(id) => {
+                    // Peers that never joined leave without a word.
+                    if let Some(peer) = self.remove(id)
+                        && peer.joined
+                    {
+                        happened.push(match self.hosting {
+                            true => SessionEvent::Left(id, peer.name),
+                            false => SessionEvent::Closed(String::from("The host left")),
+                        });
+                    }
+                },
+                // Only peers that said HELLO are listened to.
+                NetEvent::Received(id, message) => {
+                    if self.peers.iter().any(|peer| peer.id == id && peer.joined) {
+                        happened.push(SessionEvent::Received(id, message));
+                    }
+                },
+            }
+        }
+        happened
+    }
+
+    fn remove(&mut self, id: u32) -> Option<Peer> {
+        let index = self.peers.iter().position(|peer| peer.id == id)?;
+        Some(self.peers.remove(index))
+    }
+
+    // Doesn't wait for the message to go out, the peer's writer thread
+    // sends it. A peer that can't be written to is gone, its reader will tell.
+    pub fn send(&mut self, id: u32, message: &Message) {
+        if let Some(peer) = self.peers.iter().find(|peer| peer.id == id) {
+            peer.outbox.send(message.encode()).ok();
+        }
+    }
+
+    // Hosts: to every peer that joined. Clients: to the host.
+    pub fn broadcast(&mut self, message: &Message) {
+        let bytes = message.encode();
+        for peer in self.peers.iter().filter(|peer| peer.joined) {
+            peer.outbox.send(bytes.clone()).ok();
+        }
+    }
+
+    pub fn peer_names(&self) -> Vec<String> {
+        self.peers.iter().filter(|peer| peer.joined).map(|peer| peer.name.clone()).collect()
+    }
+
+    pub fn describe(&self) -> String {
+        match self.hosting {
+            true => format!("Hosting on {}, {} peers", self.address, self.peer_names().len()),
+            false => format!("Joined {} as peer {}", self.address, self.peer_id),
+        }
+    }
+
+    // Says BYE to everybody and closes the connections. The writer threads
+    // close them once the BYE is out.
+    pub fn close(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        self.broadcast(&Message::Bye);
+        self.peers.clear();
+    }
+}
+
+// Writes the messages of one connection, so a peer that doesn't read can't
+// block the game. Ends when the session drops the peer, or when a write
+// fails or takes longer than WRITE_TIMEOUT.
+fn spawn_writer(mut stream: TcpStream) -> Sender<Vec<u8>> {
+    let (outbox, messages) = channel::<Vec<u8>>();
+    stream.set_write_timeout(Some(WRITE_TIMEOUT)).ok();
+    std::thread::spawn(move || {
+        for bytes in messages {
+            if stream.write_all(&bytes).is_err() {
+                break;
+            }
+        }
+        // Its reader sees the connection close and tells the session.
+        stream.shutdown(std::net::Shutdown::Both).ok();
+    });
+    outbox
+}
+
+// Reads messages from one connection until it closes.
+// Hosts answer HELLO right here, so joining works even while the game is busy
+// with something else and nobody calls `poll`.
+fn spawn_reader(id: u32, mut reader: BufReader<TcpStream>, sender: Sender<NetEvent>, hosting: bool) {
+    std::thread::spawn(move || {
+        loop {
+            match Message::read(&mut reader) {
+                Ok(Some(Message::Hello { version, name })) if hosting => {
+                    let answer = match version == PROTOCOL_VERSION {
+                        true => Message::Welcome { version, peer: id },
+                        false => Message::Reject(format!("protocol version {PROTOCOL_VERSION} needed, not {version}")),
+                    };
+                    reader.get_ref().write_all(&answer.encode()).ok();
+                    if let Message::Reject(_) = answer {
+                        reader.get_ref().shutdown(std::net::Shutdown::Both).ok();
+                        sender.send(NetEvent::Disconnected(id)).ok();
+                        return;
+                    }
+                    if sender.send(NetEvent::Received(id, Message::Hello { version, name })).is_err() {
+                        return;
+                    }
+                },
+                Ok(Some(message)) => {
+                    if sender.send(NetEvent::Received(id, message)).is_err() {
+                        return;
+                    }
+                },
+                // Closed, or a message that makes no sense: either way the
+                // connection can't be trusted anymore.
+                _ => {
+                    sender.send(NetEvent::Disconnected(id)).ok();
+                    return;
+                },
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Cell;
+
+    fn read(text: &str) -> Result<Option<Message>, String> {
+        Message::read(&mut text.as_bytes())
+    }
+
+    #[test]
+    fn messages_read_back() {
+        let messages = vec![
+            Message::Hello { version: PROTOCOL_VERSION, name: String::from("Ada Lovelace") },
+            Message::Welcome { version: PROTOCOL_VERSION, peer: 3 },
+            Message::Reject(String::from("the game is full")),
+            Message::Snapshot { generation: 7, map: String::from("[x][ ]\r\n[ ][x]") },
+            Message::Edit { generation: 42, row: 4, col: 7, state: 1, colour: 2 },
+            Message::Rule { generation: 42, rule: String::from("B36/S23 QuadLife") },
+            Message::Step { generations: 10 },
+            Message::Tick { generation: 42, generations: 10 },
+            Message::Hash { generation: 52, hash: u64::MAX },
+            Message::Delta { generation: 43, cells: vec![(1, 2, 1, 0), (3, 4, 2, 1)] },
+            Message::Delta { generation: 44, cells: vec![] },
+            Message::Resync,
+            Message::Bye,
+        ];
+        for message in messages {
+            let text = String::from_utf8(message.encode()).unwrap();
+            assert_eq!(read(&text), Ok(Some(message)));
+        }
+    }
+
+    #[test]
+    fn messages_follow_each_other() {
+        // The map of a snapshot is read by its size, new lines and all.
+        let mut text = Message::Snapshot { generation: 1, map: String::from("[x]\r\n[ ]") }.encode();
+        text.extend(Message::Bye.encode());
+        let mut reader = text.as_slice();
+        assert!(matches!(Message::read(&mut reader), Ok(Some(Message::Snapshot { .. }))));
+        assert_eq!(Message::read(&mut reader), Ok(Some(Message::Bye)));
+        assert_eq!(Message::read(&mut reader), Ok(None));
+    }
+
+    #[test]
+    fn bad_messages_are_errors() {
+        assert!(read("HELLO\n").is_err());
+        assert!(read("EDIT 1 2\n").is_err());
+        assert!(read("STEP many\n").is_err());
+        assert!(read("TICK 1\n").is_err());
+        assert!(read("HASH 1 -5\n").is_err());
+        assert!(read("DELTA 1 6\n1 2 3\n").is_err());
+        assert!(read("DANCE 1\n").is_err());
+        assert!(read(&format!("SNAPSHOT 1 {}\n", MAX_BODY + 1)).is_err());
+        // The map is shorter than it says.
+        assert!(read("SNAPSHOT 1 100\n[x]").is_err());
+    }
+
+    #[test]
+    fn out_of_range_fields_are_rejected() {
+        // 300 doesn't fit a state, it isn't cut down to 44.
+        assert!(read("EDIT 1 2 3 300 0\n").is_err());
+        assert!(read("EDIT 1 2 3 1 5\n").is_err());
+        assert!(read("EDIT 1 2 3 -1 0\n").is_err());
+        assert!(read("STEP 4294967296\n").is_err());
+        assert!(read("TICK 4294967296 1\n").is_err());
+        assert!(read("TICK 0 4294967295\n").is_err());
+        assert!(read(&format!("TICK 0 {}\n", MAX_STEP + 1)).is_err());
+        assert!(read(&format!("TICK 0 {MAX_STEP}\n")).is_ok());
+        assert!(read("HASH 1 18446744073709551616\n").is_err());
+        assert!(read("DELTA 1 8\n1 2 256 0\n").is_err());
+        assert!(read("DELTA 1 8\n1 2 1 9\n").is_err());
+    }
+
+    #[test]
+    fn hashes_see_cells_colours_and_rule() {
+        let mut map: Vectrix = vec![vec![Cell::dead(); 4]; 4];
+        map[1][2] = Cell::alive();
+        let life = Rule::life();
+        let hash = state_hash(&map, &life);
+        assert_eq!(hash, state_hash(&map.clone(), &Rule::parse("23/3").unwrap()));
+
+        assert_ne!(hash, state_hash(&map, &Rule::parse("B36/S23").unwrap()));
+        let mut moved = map.clone();
+        moved[1][2] = Cell::dead();
+        moved[2][1] = Cell::alive();
+        assert_ne!(hash, state_hash(&moved, &life));
+        let mut coloured = map.clone();
+        coloured[1][2] = Cell::coloured(1);
+        assert_ne!(hash, state_hash(&coloured, &life));
+    }
+
+    #[test]
+    fn changes_are_the_cells_that_differ() {
+        let before: Vectrix = vec![vec![Cell::dead(); 3]; 2];
+        let mut after = before.clone();
+        after[0][2] = Cell::alive();
+        after[1][0] = Cell::coloured(2);
+        assert_eq!(changes(&before, &after), [(0, 2, 1, 0), (1, 0, 1, 2)]);
+        assert!(changes(&after, &after).is_empty());
+    }
+
+    #[test]
+    fn generation_counts_dont_overflow() {
+        let mut lockstep = Lockstep::new();
+        assert!(lockstep.advance(MAX_STEP));
+        assert_eq!(lockstep.generation, MAX_STEP);
+
+        lockstep.generation = u32::MAX - 1;
+        assert!(!lockstep.advance(2));
+        assert_eq!(lockstep.generation, u32::MAX - 1);
+        assert!(lockstep.advance(1));
+        assert_eq!(lockstep.generation, u32::MAX);
+    }
+
+    #[test]
+    fn a_peer_that_doesnt_read_doesnt_block() {
+        let port = 47_878;
+        let mut session = Session::host(port).unwrap();
+        let mut peer = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        peer.write_all(&Message::Hello { version: PROTOCOL_VERSION, name: String::from("slow") }.encode()).unwrap();
+        let mut reader = BufReader::new(peer.try_clone().unwrap());
+        assert!(matches!(Message::read(&mut reader), Ok(Some(Message::Welcome { .. }))));
+
+        let started = Instant::now();
+        while session.peer_names().is_empty() && started.elapsed() < Duration::from_secs(5) {
+            session.poll();
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(session.peer_names(), vec![String::from("slow")]);
+
+        // Far more than the connection holds. The peer reads none of it.
+        let map = "[x]".repeat(1024 * 1024);
+        let started = Instant::now();
+        for generation in 0..32 {
+            session.broadcast(&Message::Snapshot { generation, map: map.clone() });
+        }
+        assert!(started.elapsed() < Duration::from_secs(2));
+        session.close();
+    }
+}
@@ -0,0 +1,634 @@
+// Patterns and the built-in pattern catalog.
+//
+// Patterns are RLE files in the `patterns/` folder, bundled into the binary
+// by build.rs. Besides the usual `#N` (name) and `#O` (discoverer) lines,
+// the `#C` comments can carry `Key: value` pairs:
+//
+//     #N Glider
+//     #O Richard K. Guy
+//     #C Category: Spaceship
+//     #C Period: 4
+//     #C Velocity: c/4 diagonal
+//     x = 3, y = 3, rule = B3/S23
+//     o$obo$2o!
+//
+// Methuselahs use `Lifespan` and `Final population` instead of a period.
+// `Pattern::verify()` checks a pattern does what its file says. The tests at
+// the bottom run it on every bundled pattern, `--verify-patterns` does the same
+// from the command line.
+//
+// Patterns saved by the user go to the `user_patterns/` folder, in the same
+// format, and are read from there every time the catalog is listed.
+
+use crate::clipboard::Clipboard;
+use crate::plane::Plane;
+use crate::speed::Velocity;
+use crate::Coordinates;
+
+include!(concat!(env!("OUT_DIR"), "/patterns.rs"));
+
+pub const USER_LIBRARY_DIR: &str = "user_patterns";
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Category {
+    StillLife,
+    Oscillator,
+    Spaceship,
+    Methuselah,
+    Gun,
+    Other,
+}
+
+impl Category {
+    pub const ALL: [Category; 6] = [
+        Category::StillLife,
+        Category::Oscillator,
+        Category::Spaceship,
+        Category::Methuselah,
+        Category::Gun,
+        Category::Other,
+    ];
+
+    pub fn name(&self) -> &str {
+        match self {
+            Category::StillLife => "Still life",
+            Category::Oscillator => "Oscillator",
+            Category::Spaceship => "Spaceship",
+            Category::Methuselah => "Methuselah",
+            Category::Gun => "Gun",
+            Category::Other => "Other",
+        }
+    }
+
+    fn from_name(name: &str) -> Category {
+        match name.trim().to_lowercase().as_str() {
+            "still life" => Category::StillLife,
+            "oscillator" => Category::Oscillator,
+            "spaceship" => Category::Spaceship,
+            "methuselah" => Category::Methuselah,
+            "gun" => Category::Gun,
+            _ => Category::Other,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Pattern {
+    pub name: String,
+    pub category: Category,
+    pub period: Option<u32>,
+    pub velocity: Option<String>,
+    pub discoverer: Option<String>,
+    // Methuselahs: generation where it settles down and how many cells are left.
+    pub lifespan: Option<u32>,
+    pub final_population: Option<usize>,
+    pub notes: Option<String>,
+    // Saved by the user, not bundled.
+    pub user: bool,
+    pub rows: usize,
+    pub cols: usize,
+    // Live cells, relative to the top left corner.
+    pub cells: Vec<Coordinates>,
+}
+
+impl Pattern {
+    pub fn parse_rle(text: &str) -> Result<Pattern, String> {
+        let mut pattern = Pattern {
+            name: String::from("Unnamed"),
+            category: Category::Other,
+            period: None,
+            velocity: None,
+            discoverer: None,
+            lifespan: None,
+            final_population: None,
+            notes: None,
+            user: false,
+            rows: 0,
+            cols: 0,
+            cells: Vec::new(),
+        };
+
+        let mut body = String::new();
+        let mut header_found = false;
+
+        for line in text.lines() {
+            let line = line.trim();
+            if let Some(name) = line.strip_prefix("#N") {
+                pattern.name = name.trim().to_string();
+            } else if let Some(discoverer) = line.strip_prefix("#O") {
+                pattern.discoverer = Some(discoverer.trim().to_string());
+            } else if let Some(comment) = line.strip_prefix("#C") {
+                let (key, value) = match comment.split_once(':') {
+                    Some(pair) => pair,
+                    None => continue,
+                };
+                match key.trim().to_lowercase().as_str() {
+                    "category" => pattern.category = Category::from_name(value),
+                    "period" => pattern.period = value.trim().parse::<u32>().ok(),
+                    "velocity" => pattern.velocity = Some(value.trim().to_string()),
+                    "lifespan" => pattern.lifespan = value.trim().parse::<u32>().ok(),
+                    "final population" => pattern.final_population = value.trim().parse::<usize>().ok(),
+                    "notes" => pattern.notes = Some(value.trim().to_string()),
+                    _ => (),
+                }
+            } else if line.starts_with('#') || line.is_empty() {
+                continue;
+            } else if !header_found && line.starts_with('x') {
+                header_found = true;
+            } else {
+                body.push_str(line);
+            }
+        }
+
+        if !header_found {
+            return Err(format!("{}: missing `x = .., y = ..` line.", pattern.name));
+        }
+
+        // Runs of `b` (dead) and `o` (alive), `$` ends a row and `!` the pattern.
+        let (mut row, mut col) = (0, 0);
+        let mut count = String::new();
+        for ch in body.chars() {
+            if ch.is_ascii_digit() {
+                count.push(ch);
+                continue;
+            }
+            let run = count.parse::<usize>().unwrap_or(1);
+            count.clear();
+            match ch {
+                'b' | '.' => col += run,
+                'o' | 'A' => {
+                    for _ in 0..run {
+                        pattern.cells.push(Coordinates::new(row, col));
+                        col += 1;
+                    }
+                },
+                '$' => {
+                    row += run;
+                    col = 0;
+                },
+                '!' => break,
+                _ => return Err(format!("{}: unexpected `{ch}` in RLE.", pattern.name)),
+            }
+        }
+
+        let (shape, _) = Clipboard::from_points(pattern.cells.clone());
+        (pattern.rows, pattern.cols) = (shape.rows, shape.cols);
+        Ok(pattern)
+    }
+
+    pub fn to_rle(&self) -> String {
+        let mut text = format!("#N {}\n", self.name);
+        if let Some(discoverer) = &self.discoverer {
+            text.push_str(&format!("#O {discoverer}\n"));
+        }
+        text.push_str(&format!("#C Category: {}\n", self.category.name()));
+        if let Some(period) = self.period {
+            text.push_str(&format!("#C Period: {period}\n"));
+        }
+        if let Some(velocity) = &self.velocity {
+            text.push_str(&format!("#C Velocity: {velocity}\n"));
+        }
+        if let Some(notes) = &self.notes {
+            text.push_str(&format!("#C Notes: {notes}\n"));
+        }
+        text.push_str(&format!("x = {}, y = {}, rule = B3/S23\n", self.cols, self.rows));
+
+        let mut grid = vec![vec![false; self.cols]; self.rows];
+        for cell in &self.cells {
+            grid[cell.row][cell.col] = true;
+        }
+
+        // Runs of the same state, one row at a time. Dead cells at the end
+        // of a row are left out, and empty rows fold into the `$` count.
+        let mut runs = Vec::<(usize, char)>::new();
+        for row in grid {
+            let last = match row.iter().rposition(|alive| *alive) {
+                Some(last) => last,
+                None => {
+                    push_run(&mut runs, 1, '$');
+                    continue;
+                },
+            };
+            for alive in &row[..=last] {
+                push_run(&mut runs, 1, if *alive { 'o' } else { 'b' });
+            }
+            push_run(&mut runs, 1, '$');
+        }
+        // The last row ends with `!`, not `$`.
+        while let Some((_, '$')) = runs.last() {
+            runs.pop();
+        }
+        runs.push((1, '!'));
+
+        // RLE lines are kept under 70 characters.
+        let mut line = String::new();
+        for (count, ch) in runs {
+            let run = match count {
+                1 => ch.to_string(),
+                _ => format!("{count}{ch}"),
+            };
+            if line.len() + run.len() > 70 {
+                text.push_str(&line);
+                text.push('\n');
+                line.clear();
+            }
+            line.push_str(&run);
+        }
+        text.push_str(&line);
+        text.push('\n');
+        text
+    }
+
+    // One line about the pattern, for menus.
+    pub fn describe(&self) -> String {
+        let mut details = vec![self.category.name().to_string()];
+        if let Some(period) = self.period {
+            details.push(format!("period {period}"));
+        }
+        if let Some(velocity) = &self.velocity {
+            details.push(velocity.clone());
+        }
+        if let Some(lifespan) = self.lifespan {
+            details.push(format!("lifespan {lifespan}"));
+        }
+        if let Some(discoverer) = &self.discoverer {
+            details.push(format!("found by {discoverer}"));
+        }
+        if let Some(notes) = &self.notes {
+            details.push(notes.clone());
+        }
+        format!("{} ({})", self.name, details.join(", "))
+    }
+
+    // The pattern's cells oriented and placed with its top left corner at `origin`.
+    pub fn points_at(&self, origin: &Coordinates, orientation: &Orientation) -> Vec<Coordinates> {
+        let (mut shape, _) = Clipboard::from_points(self.cells.clone());
+        if orientation.flipped {
+            shape.flip_horizontal();
+        }
+        for _ in 0..orientation.rotations {
+            shape.rotate();
+        }
+        shape.points_at(origin)
+    }
+
+    // Run the pattern on the unbounded plane and check it behaves as documented.
+    // Ok has what was checked, Err what went wrong.
+    pub fn verify(&self) -> Result<String, String> {
+        let start = Plane::from_points(&self.cells);
+
+        match self.category {
+            Category::StillLife => {
+                if start.step() != start {
+                    return Err(String::from("changes after one generation"));
+                }
+                Ok(String::from("still life"))
+            },
+            Category::Oscillator => {
+                let period = self.period.ok_or("no period")?;
+                let found = repeat(&start, period)?;
+                if found.period != period || found.is_moving() {
+                    return Err(format!("repeats after {} generations, shifted {:?}", found.period, found.shift));
+                }
+                Ok(format!("period {period}"))
+            },
+            Category::Spaceship => {
+                let period = self.period.ok_or("no period")?;
+                let velocity = self.velocity.as_deref().ok_or("no velocity")?;
+                let found = repeat(&start, period)?;
+                if found.period != period {
+                    return Err(format!("repeats after {} generations", found.period));
+                }
+                if found.notation() != velocity.trim() {
+                    return Err(format!("moved {:?} in {period} generations ({}), not {velocity}", found.shift, found.notation()));
+                }
+                Ok(format!("period {period}, moves {:?}, {velocity}", found.shift))
+            },
+            Category::Methuselah => {
+                let lifespan = self.lifespan.ok_or("no lifespan")?;
+                let final_population = self.final_population.ok_or("no final population")?;
+
+                // It must reach its final population exactly at its lifespan
+                // and stay there for a while.
+                let mut plane = start;
+                let mut previous = plane.population();
+                for generation in 1..=lifespan + 100 {
+                    plane = plane.step();
+                    let population = plane.population();
+                    if generation == lifespan && (population != final_population || previous == final_population) {
+                        return Err(format!("population {previous} -> {population} at generation {lifespan}"));
+                    }
+                    if generation > lifespan && population != final_population {
+                        return Err(format!("population {population} at generation {generation}"));
+                    }
+                    previous = population;
+                }
+                Ok(format!("lifespan {lifespan}, final population {final_population}"))
+            },
+            Category::Gun => {
+                let period = self.period.ok_or("no period")?;
+
+                // One more glider (5 cells) every period.
+                let mut plane = start;
+                let mut previous = plane.population();
+                for shot in 1..=4 {
+                    for _ in 0..period {
+                        plane = plane.step();
+                    }
+                    if plane.population() != previous + 5 {
+                        return Err(format!("population {previous} -> {} in shot {shot}", plane.population()));
+                    }
+                    previous = plane.population();
+                }
+                Ok(format!("period {period}, one glider per period"))
+            },
+            Category::Other => Ok(String::from("nothing to check")),
+        }
+    }
+}
+
+// How the pattern repeats, if it does within `limit` generations.
+fn repeat(start: &Plane, limit: u32) -> Result<Velocity, String> {
+    Velocity::measure(start, limit).ok_or(format!("doesn't repeat in {limit} generations"))
+}
+
+fn push_run(runs: &mut Vec<(usize, char)>, count: usize, ch: char) {
+    match runs.last_mut() {
+        Some((last_count, last_ch)) if *last_ch == ch => *last_count += count,
+        _ => runs.push((count, ch)),
+    }
+}
+
+// Patterns in the user library. A missing folder is just an empty library.
+pub fn user_library() -> (Vec<Pattern>, Vec<String>) {
+    use std::fs::{read_dir, read_to_string};
+
+    let mut patterns = Vec::<Pattern>::new();
+    let mut errors = Vec::<String>::new();
+
+    let entries = match read_dir(USER_LIBRARY_DIR) {
+        Ok(entries) => entries,
+        Err(_) => return (patterns, errors),
+    };
+
+    for path in entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+        if path.extension().is_none_or(|ext| ext != "rle") {
+            continue;
+        }
+        let parsed = read_to_string(&path)
+            .map_err(|error| error.to_string())
+            .and_then(|text| Pattern::parse_rle(&text));
+        match parsed {
+            Ok(mut pattern) => {
+                pattern.user = true;
+                patterns.push(pattern);
+            },
+            Err(error) => errors.push(format!("[-] {}: {error}", path.display())),
+        }
+    }
+    (patterns, errors)
+}
+
+// Where a pattern called `name` is saved in the user library. Different
+// names can end up in the same file, e.g. "My Ship" and "my_ship".
+pub fn library_path(name: &str) -> std::path::PathBuf {
+    let file_name: String = name
+        .to_lowercase()
+        .chars()
+        .map(|ch| if ch.is_ascii_alphanumeric() { ch } else { '_' })
+        .collect();
+    std::path::Path::new(USER_LIBRARY_DIR).join(format!("{file_name}.rle"))
+}
+
+// Save to the user library, see `library_path`. An existing file is only
+// replaced if `overwrite` is set.
+pub fn save_to_library(pattern: &Pattern, overwrite: bool) -> String {
+    use std::fs::{create_dir_all, write};
+
+    let path = library_path(&pattern.name);
+    if path.exists() && !overwrite {
+        return format!("[-] {} already exists, not saved.", path.display());
+    }
+
+    if create_dir_all(USER_LIBRARY_DIR).is_err() {
+        return format!("[-] Failed to create {USER_LIBRARY_DIR}/.");
+    }
+    match write(&path, pattern.to_rle()) {
+        Ok(_) => format!("[+] {} saved to {}.", pattern.name, path.display()),
+        Err(_) => format!("[-] Failed to save {}.", path.display()),
+    }
+}
+
+// Checks every bundled pattern. Prints a line per pattern and returns
+// false if any of them failed.
+pub fn verify_catalog() -> bool {
+    let (patterns, errors) = catalog();
+    let mut all_ok = errors.is_empty();
+
+    for error in errors {
+        println!("{error}");
+    }
+    for pattern in patterns {
+        match pattern.verify() {
+            Ok(checked) => println!("[+] {}: {checked}", pattern.name),
+            Err(error) => {
+                println!("[-] {}: {error}", pattern.name);
+                all_ok = false;
+            },
+        }
+    }
+    all_ok
+}
+
+// Rotation and mirroring of a pattern before it is placed.
+#[derive(Clone, Copy)]
+pub struct Orientation {
+    // Quarter turns clockwise
+    pub rotations: u8,
+    // Mirrored left to right before rotating
+    pub flipped: bool,
+}
+
+impl Orientation {
+    pub fn describe(&self) -> String {
+        let flipped = match self.flipped {
+            true => ", flipped",
+            false => "",
+        };
+        format!("rotated {} degrees{flipped}", self.rotations as u32 * 90)
+    }
+}
+
+// Every bundled pattern and every pattern in the user library,
+// sorted by category and name.
+pub fn full_catalog() -> (Vec<Pattern>, Vec<String>) {
+    let (mut patterns, mut errors) = catalog();
+    let (user_patterns, user_errors) = user_library();
+    patterns.extend(user_patterns);
+    errors.extend(user_errors);
+
+    patterns.sort_by(|a, b| (a.category, &a.name).cmp(&(b.category, &b.name)));
+    (patterns, errors)
+}
+
+// Every bundled pattern, sorted by category and name.
+// A file that fails to parse is reported instead of silently skipped.
+pub fn catalog() -> (Vec<Pattern>, Vec<String>) {
+    let mut patterns = Vec::<Pattern>::new();
+    let mut errors = Vec::<String>::new();
+
+    for (file, text) in BUNDLED_PATTERNS {
+        match Pattern::parse_rle(text) {
+            Ok(pattern) => patterns.push(pattern),
+            Err(error) => errors.push(format!("[-] {file}: {error}")),
+        }
+    }
+
+    patterns.sort_by(|a, b| (a.category, &a.name).cmp(&(b.category, &b.name)));
+    (patterns, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GLIDER: &str = "#N Glider\n#O Richard K. Guy\n#C Category: Spaceship\n#C Period: 4\n#C Velocity: c/4 diagonal\nx = 3, y = 3, rule = B3/S23\nbob$2bo$3o!";
+
+    fn cells(points: &[Coordinates]) -> Vec<(usize, usize)> {
+        let mut cells: Vec<(usize, usize)> = points.iter().map(|point| (point.row, point.col)).collect();
+        cells.sort();
+        cells
+    }
+
+    fn bundled(name: &str) -> Pattern {
+        let (patterns, _) = catalog();
+        patterns.into_iter().find(|pattern| pattern.name == name).unwrap_or_else(|| panic!("no pattern {name}"))
+    }
+
+    // Every bundled pattern of `category` does what its file says.
+    fn verify_category(category: Category) {
+        let (patterns, _) = catalog();
+        let failed: Vec<String> = patterns
+            .iter()
+            .filter(|pattern| pattern.category == category)
+            .filter_map(|pattern| pattern.verify().err().map(|error| format!("{}: {error}", pattern.name)))
+            .collect();
+        assert!(failed.is_empty(), "{failed:?}");
+    }
+
+    #[test]
+    fn every_bundled_pattern_parses() {
+        let (patterns, errors) = catalog();
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(patterns.len(), BUNDLED_PATTERNS.len());
+    }
+
+    #[test]
+    fn rle_with_details() {
+        let glider = Pattern::parse_rle(GLIDER).unwrap();
+        assert_eq!(glider.name, "Glider");
+        assert!(glider.category == Category::Spaceship);
+        assert_eq!(glider.period, Some(4));
+        assert_eq!(glider.velocity.as_deref(), Some("c/4 diagonal"));
+        assert_eq!((glider.rows, glider.cols), (3, 3));
+        assert_eq!(cells(&glider.cells), [(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+        assert_eq!(glider.describe(), "Glider (Spaceship, period 4, c/4 diagonal, found by Richard K. Guy)");
+    }
+
+    #[test]
+    fn saved_rle_reads_back() {
+        // Empty rows in the middle and a long run, to check the `$` counts.
+        let text = "#N Spread out\n#C Category: Other\n#C Notes: two rows apart\nx = 40, y = 4, rule = B3/S23\no3$40o!";
+        let pattern = Pattern::parse_rle(text).unwrap();
+        let saved = pattern.to_rle();
+        assert!(saved.contains("o3$40o!"), "{saved}");
+
+        let read_back = Pattern::parse_rle(&saved).unwrap();
+        assert_eq!(read_back.name, "Spread out");
+        assert_eq!(read_back.notes.as_deref(), Some("two rows apart"));
+        assert_eq!((read_back.rows, read_back.cols), (4, 40));
+        assert_eq!(cells(&read_back.cells), cells(&pattern.cells));
+    }
+
+    #[test]
+    fn bad_rle_is_an_error() {
+        assert!(Pattern::parse_rle("#N No header\nbo$2bo!").is_err());
+        assert!(Pattern::parse_rle("x = 2, y = 1\nbz!").is_err());
+    }
+
+    #[test]
+    fn orientations() {
+        let glider = Pattern::parse_rle(GLIDER).unwrap();
+        let turned = Orientation { rotations: 1, flipped: false };
+        assert_eq!(cells(&glider.points_at(&Coordinates::new(10, 20), &turned)), [(10, 20), (11, 20), (11, 22), (12, 20), (12, 21)]);
+
+        let flipped = Orientation { rotations: 0, flipped: true };
+        assert_eq!(cells(&glider.points_at(&Coordinates::new(0, 0), &flipped)), [(0, 1), (1, 0), (2, 0), (2, 1), (2, 2)]);
+        assert_eq!(Orientation { rotations: 3, flipped: true }.describe(), "rotated 270 degrees, flipped");
+    }
+
+    #[test]
+    fn still_lifes_verify() {
+        verify_category(Category::StillLife);
+    }
+
+    #[test]
+    fn oscillators_verify() {
+        verify_category(Category::Oscillator);
+    }
+
+    #[test]
+    fn spaceships_verify() {
+        verify_category(Category::Spaceship);
+    }
+
+    #[test]
+    fn methuselahs_verify() {
+        verify_category(Category::Methuselah);
+    }
+
+    #[test]
+    fn guns_verify() {
+        verify_category(Category::Gun);
+    }
+
+    #[test]
+    fn oscillator_periods() {
+        for (name, period) in [("Blinker", 2), ("Toad", 2), ("Pulsar", 3), ("Pentadecathlon", 15)] {
+            let found = Velocity::measure(&Plane::from_points(&bundled(name).cells), period).unwrap();
+            assert_eq!(found.period, period, "{name}");
+            assert!(!found.is_moving(), "{name}");
+        }
+    }
+
+    #[test]
+    fn lightweight_spaceship_speed() {
+        let lwss = bundled("Lightweight spaceship");
+        let found = Velocity::measure(&Plane::from_points(&lwss.cells), 4).unwrap();
+        assert_eq!(found.period, 4);
+        assert_eq!(found.shift.0.abs() + found.shift.1.abs(), 2);
+        assert_eq!(found.notation(), "c/2 orthogonal");
+    }
+
+    #[test]
+    fn names_can_share_a_library_file() {
+        assert_eq!(library_path("My Ship"), library_path("my_ship"));
+        assert_ne!(library_path("My Ship"), library_path("My Boat"));
+    }
+
+    #[test]
+    fn wrong_details_fail() {
+        let mut glider = bundled("Glider");
+        glider.velocity = Some(String::from("c/2 orthogonal"));
+        assert!(glider.verify().is_err());
+
+        let mut blinker = bundled("Blinker");
+        blinker.period = Some(3);
+        assert!(blinker.verify().is_err());
+
+        let mut paperclip = bundled("Fifteen bent paperclip");
+        paperclip.cells.push(Coordinates::new(8, 8));
+        paperclip.cells.push(Coordinates::new(8, 9));
+        assert!(paperclip.verify().is_err());
+    }
+}
@@ -0,0 +1,73 @@
+// The unbounded plane.
+//
+// The map is a torus: patterns wrap around the edges and eventually run into
+// themselves. To check what a pattern does on its own (period, speed,
+// lifespan) it is run here instead, where only the live cells are stored
+// and nothing ever wraps.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::Coordinates;
+
+#[derive(Clone, PartialEq)]
+pub struct Plane {
+    pub cells: HashSet<(i64, i64)>,
+}
+
+impl Plane {
+    pub fn from_points(points: &[Coordinates]) -> Plane {
+        Plane {
+            cells: points
+                .iter()
+                .map(|point| (point.row as i64, point.col as i64))
+                .collect(),
+        }
+    }
+
+    pub fn population(&self) -> usize {
+        self.cells.len()
+    }
+
+    // Next generation, B3/S23.
+    pub fn step(&self) -> Plane {
+        let mut neighbors = HashMap::<(i64, i64), u32>::new();
+        for (row, col) in &self.cells {
+            for d_row in -1..=1 {
+                for d_col in -1..=1 {
+                    if d_row == 0 && d_col == 0 {
+                        continue;
+                    }
+                    *neighbors.entry((row + d_row, col + d_col)).or_insert(0) += 1;
+                }
+            }
+        }
+
+        Plane {
+            cells: neighbors
+                .into_iter()
+                .filter(|(cell, count)| *count == 3 || (*count == 2 && self.cells.contains(cell)))
+                .map(|(cell, _)| cell)
+                .collect(),
+        }
+    }
+
+    // Top left corner of the bounding box.
+    pub fn corner(&self) -> (i64, i64) {
+        let row = self.cells.iter().map(|(row, _)| *row).min().unwrap_or(0);
+        let col = self.cells.iter().map(|(_, col)| *col).min().unwrap_or(0);
+        (row, col)
+    }
+
+    // The cells moved so the bounding box starts at (0, 0), sorted.
+    // Two planes with the same shape give the same list wherever they are.
+    pub fn shape(&self) -> Vec<(i64, i64)> {
+        let (top, left) = self.corner();
+        let mut shape: Vec<(i64, i64)> = self
+            .cells
+            .iter()
+            .map(|(row, col)| (row - top, col - left))
+            .collect();
+        shape.sort();
+        shape
+    }
+}
@@ -0,0 +1,955 @@
+// Rules: how many neighbours make a cell be born or survive.
+//
+// Conway's Game of Life is B3/S23: born with 3 neighbours, survives with 2
+// or 3. Any other Life-like rule is written the same way, and the old style
+// with survival first, `23/3`, is accepted too.
+//
+// The Generations family adds dying states. A live cell that doesn't survive
+// doesn't die at once: it goes through the dying states, one per generation,
+// and then it is dead. Dying cells can't be born again or counted as
+// neighbours. The number of states (alive, dead and the dying ones) is given
+// with C, like Brian's Brain `B2/S/C3`, or as a third number with survival
+// first, like Star Wars `345/2/4`.
+//
+// The neighbours are the 8 cells around (Moore neighbourhood) unless the rule
+// ends with a letter, like Golly does:
+//
+//     B2/S34V    von Neumann, the 4 cells up, down, left and right
+//     B2/S34H    hexagonal, 6 cells
+//
+// or has a mask of weights, rows split by commas and the cell itself in the
+// middle. When the weights add up to 10 or more, counts are split by commas
+// too:
+//
+//     B3,4/S2,3,4/N121,202,121    corners count 1, sides count 2
+//
+// Larger than Life rules count every cell up to R cells away. They use the
+// same format as Golly, e.g. Bosco's Rule:
+//
+//     R5,C0,M1,S34..58,B34..45,NM
+//
+// R is the radius, C the number of states (0 or 2 for no dying states), M1
+// counts the cell itself and M0 doesn't, S and B are ranges of counts to
+// survive and to be born, and N is the shape: M for a square, N for a
+// diamond, C for a circle.
+//
+// Isotropic non-totalistic rules (Hensel notation) look at where the
+// neighbours are, not only how many there are. After a count, letters pick
+// some of the ways to place that many neighbours, up to rotations and
+// reflections, and a `-` before the letters leaves them out instead:
+//
+//     B2-a/S12      born with 2 neighbours unless they are side by side
+//     B3/S2-i34q    tlife
+//
+// There are 51 of those ways over the counts 0 to 8, see HENSEL below. They
+// only work with the Moore neighbourhood.
+//
+// Immigration and QuadLife give live cells a colour, 2 and 4 of them. A
+// newborn takes the colour most of its parents have, and in QuadLife 3
+// parents of 3 different colours give the fourth one. Alone they play Life;
+// after another rule, like `B36/S23 QuadLife`, they colour that one.
+//
+// Anything else can be written as a Golly rule table, see table.rs. Those
+// are given by name, like `WireWorld` or `Langtons-Loops`, or as a path
+// ending in `.rule`.
+
+use crate::table::RuleTable;
+
+pub const MAX_STATES: u32 = 255;
+pub const MAX_RADIUS: u32 = 50;
+
+// The 8 neighbours in Hensel's order, clockwise from the one above:
+// N, NE, E, SE, S, SW, W, NW. Bit `n` of a configuration is RING[n].
+pub const RING: [(i64, i64); 8] = [(-1, 0), (-1, 1), (0, 1), (1, 1), (1, 0), (1, -1), (0, -1), (-1, -1)];
+
+// One configuration of every letter for 1 to 4 neighbours, in RING order.
+// The rest of its class are its rotations and reflections. 5 to 7
+// neighbours use the letter of the cells that are dead, e.g. 6a is 2a upside
+// down: every cell alive but two side by side.
+const HENSEL: [(char, &str); 31] = [
+    ('e', "10000000"), ('c', "01000000"),
+    ('a', "11000000"), ('e', "10100000"), ('k', "10010000"), ('i', "10001000"), ('c', "01010000"), ('n', "01000100"),
+    ('a', "11100000"), ('n', "11010000"), ('r', "11001000"), ('q', "11000100"), ('j', "11000010"), ('i', "11000001"),
+    ('e', "10101000"), ('k', "10100100"), ('y', "10010100"), ('c', "01010100"),
+    ('a', "11110000"), ('r', "11101000"), ('q', "11100100"), ('i', "11011000"), ('y', "11010100"), ('k', "11010010"),
+    ('n', "11010001"), ('z', "11001100"), ('j', "11001010"), ('t', "10011100"), ('w', "11000110"), ('e', "10101010"),
+    ('c', "01010101"),
+];
+
+// Names of the coloured variants and how many colours they have.
+const COLOUR_RULES: [(&str, u8); 2] = [("Immigration", 2), ("QuadLife", 4)];
+
+// Order the letters are written in.
+const HENSEL_LETTERS: &str = "cekainyqjrtwz";
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum RangeShape {
+    // Square
+    Moore,
+    // Diamond
+    VonNeumann,
+    // Circle of radius R + 1/2
+    Circular,
+}
+
+#[derive(Clone, PartialEq)]
+pub enum Neighbourhood {
+    Moore,
+    VonNeumann,
+    // Odd rows are drawn half a cell to the right, so every cell touches
+    // 2 cells in the row above, 2 beside and 2 below.
+    Hexagonal,
+    // Square of weights with an odd side, the cell itself in the middle.
+    Custom(Vec<Vec<u32>>),
+    // Larger than Life. `middle` counts the cell itself.
+    Range {
+        radius: u32,
+        shape: RangeShape,
+        middle: bool,
+    },
+}
+
+impl Neighbourhood {
+    // (rows, cols, weight) of every neighbour. Hexagonal neighbours depend
+    // on the row being odd or even.
+    pub fn offsets(&self, odd_row: bool) -> Vec<(i64, i64, u32)> {
+        match self {
+            Neighbourhood::Moore => {
+                let mut offsets = vec![];
+                for d_row in -1..=1 {
+                    for d_col in -1..=1 {
+                        if d_row != 0 || d_col != 0 {
+                            offsets.push((d_row, d_col, 1));
+                        }
+                    }
+                }
+                offsets
+            },
+            Neighbourhood::VonNeumann => vec![(-1, 0, 1), (0, -1, 1), (0, 1, 1), (1, 0, 1)],
+            Neighbourhood::Hexagonal => {
+                // Columns of the cells above and below.
+                let (left, right) = match odd_row {
+                    true => (0, 1),
+                    false => (-1, 0),
+                };
+                vec![
+                    (-1, left, 1), (-1, right, 1),
+                    (0, -1, 1), (0, 1, 1),
+                    (1, left, 1), (1, right, 1),
+                ]
+            },
+            Neighbourhood::Custom(mask) => {
+                let middle = (mask.len() / 2) as i64;
+                let mut offsets = vec![];
+                for (i, row) in mask.iter().enumerate() {
+                    for (j, weight) in row.iter().enumerate() {
+                        if *weight > 0 {
+                            offsets.push((i as i64 - middle, j as i64 - middle, *weight));
+                        }
+                    }
+                }
+                offsets
+            },
+            Neighbourhood::Range { radius, shape, middle } => {
+                let r = *radius as i64;
+                let mut offsets = vec![];
+                for d_row in -r..=r {
+                    for d_col in -r..=r {
+                        let inside = match shape {
+                            RangeShape::Moore => true,
+                            RangeShape::VonNeumann => d_row.abs() + d_col.abs() <= r,
+                            RangeShape::Circular => d_row * d_row + d_col * d_col <= r * r + r,
+                        };
+                        if inside && (*middle || d_row != 0 || d_col != 0) {
+                            offsets.push((d_row, d_col, 1));
+                        }
+                    }
+                }
+                offsets
+            },
+        }
+    }
+
+    // Highest possible count.
+    fn max_count(&self) -> u32 {
+        self.offsets(false).iter().map(|(_, _, weight)| weight).sum()
+    }
+
+    fn parse_mask(text: &str) -> Result<Neighbourhood, String> {
+        let mut mask = Vec::<Vec<u32>>::new();
+        for row in text.split(',') {
+            let weights: Option<Vec<u32>> = row.chars().map(|ch| ch.to_digit(10)).collect();
+            match weights {
+                Some(weights) => mask.push(weights),
+                None => return Err(format!("mask row `{row}` must be digits")),
+            }
+        }
+
+        let side = mask.len();
+        if side.is_multiple_of(2) || mask.iter().any(|row| row.len() != side) {
+            return Err(String::from("the mask must be a square with an odd side, like 111,101,111"));
+        }
+        Ok(Neighbourhood::Custom(mask))
+    }
+
+    fn write(&self) -> String {
+        match self {
+            Neighbourhood::Moore => String::new(),
+            Neighbourhood::VonNeumann => String::from("V"),
+            Neighbourhood::Hexagonal => String::from("H"),
+            Neighbourhood::Custom(mask) => {
+                let rows: Vec<String> = mask
+                    .iter()
+                    .map(|row| row.iter().map(|weight| weight.to_string()).collect())
+                    .collect();
+                format!("/N{}", rows.join(","))
+            },
+            // Written with the rest of the rule, see `Rule::write`.
+            Neighbourhood::Range { .. } => String::new(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Rule {
+    // Rule string, always written as B.../S... (and /C... for Generations).
+    pub name: String,
+    // Indexed by number of live neighbours (or sum of weights), or by the
+    // configuration of the neighbours for non-totalistic rules.
+    birth: Vec<bool>,
+    survival: Vec<bool>,
+    // 2 for Life-like rules. Generations rules have `states - 2` dying states.
+    pub states: u8,
+    pub neighbourhood: Neighbourhood,
+    // Hensel notation, see `configuration` in main.rs.
+    pub non_totalistic: bool,
+    // Rule tables don't use any of the above but `states`.
+    pub table: Option<RuleTable>,
+    // 2 for Immigration, 4 for QuadLife, 0 for rules without colours.
+    pub colours: u8,
+}
+
+impl Rule {
+    pub fn life() -> Rule {
+        Rule::parse("B3/S23").unwrap()
+    }
+
+    pub fn parse(text: &str) -> Result<Rule, String> {
+        let text = text.trim();
+        if text.ends_with(".rule") {
+            return Rule::from_table(text);
+        }
+
+        for (word, colours) in COLOUR_RULES {
+            let Some(start) = text.len().checked_sub(word.len()) else {
+                continue;
+            };
+            if !text.get(start..).is_some_and(|tail| tail.eq_ignore_ascii_case(word)) {
+                continue;
+            }
+            let mut rule = match text[..start].trim() {
+                "" => Rule::life(),
+                base => Rule::parse(base)?,
+            };
+            if rule.states > 2 || rule.table.is_some() || rule.colours > 0 {
+                return Err(format!("{word} only works with rules of two states"));
+            }
+            rule.colours = colours;
+            rule.name = rule.write();
+            return Ok(rule);
+        }
+
+        match Rule::parse_rulestring(text) {
+            Ok(rule) => Ok(rule),
+            // Maybe the name of a bundled rule table.
+            Err(error) => Rule::from_table(text).map_err(|_| error),
+        }
+    }
+
+    // Rules from other computers: like `parse`, but tables only by the name
+    // of a bundled one, so nobody can make this computer read its files.
+    pub fn parse_remote(text: &str) -> Result<Rule, String> {
+        if text.contains(".rule") {
+            return Err(String::from("only bundled rule tables can be sent, not .rule files"));
+        }
+        Rule::parse(text)
+    }
+
+    fn from_table(name: &str) -> Result<Rule, String> {
+        let table = RuleTable::find(name)?;
+        Ok(Rule {
+            // Paths are kept so saved maps can find the file again.
+            name: match name.ends_with(".rule") {
+                true => name.to_string(),
+                false => table.name.clone(),
+            },
+            birth: vec![],
+            survival: vec![],
+            states: table.states,
+            neighbourhood: table.neighbourhood.clone(),
+            non_totalistic: false,
+            table: Some(table),
+            colours: 0,
+        })
+    }
+
+    fn parse_rulestring(text: &str) -> Result<Rule, String> {
+        // Hensel letters are lower case, the rest is upper case.
+        let mut text = text.trim().to_string();
+
+        // R5,C0,...
+        if text.to_uppercase().starts_with('R') && text.contains(',') {
+            return Rule::parse_larger_than_life(&text.to_uppercase());
+        }
+
+        let mut neighbourhood = Neighbourhood::Moore;
+        if text.ends_with(['V', 'v']) {
+            neighbourhood = Neighbourhood::VonNeumann;
+            text.pop();
+        } else if text.ends_with(['H', 'h']) {
+            neighbourhood = Neighbourhood::Hexagonal;
+            text.pop();
+        }
+
+        let parts: Vec<&str> = text.split('/').collect();
+
+        let mut birth = "";
+        let mut survival = "";
+        let mut states = "2";
+
+        if parts.iter().any(|part| part.starts_with(['B', 'S', 'C', 'G', 'N', 'b', 's', 'c', 'g', 'n'])) {
+            for part in &parts {
+                match part.chars().next().map(|ch| ch.to_ascii_uppercase()) {
+                    Some('B') => birth = &part[1..],
+                    Some('S') => survival = &part[1..],
+                    Some('C') | Some('G') => states = &part[1..],
+                    Some('N') => neighbourhood = Neighbourhood::parse_mask(&part[1..])?,
+                    _ => return Err(format!("don't know what `{part}` is")),
+                }
+            }
+        } else {
+            match parts.as_slice() {
+                [s, b] => (survival, birth) = (s, b),
+                [s, b, c] => (survival, birth, states) = (s, b, c),
+                _ => return Err(String::from("expected B.../S... or survival/birth")),
+            }
+        }
+
+        let states = match states.parse::<u32>() {
+            Ok(states) if (2..=MAX_STATES).contains(&states) => states as u8,
+            _ => return Err(format!("the number of states must be 2 to {MAX_STATES}")),
+        };
+
+        let (birth, survival) = (birth.to_lowercase(), survival.to_lowercase());
+        let non_totalistic = birth.contains(char::is_alphabetic) || survival.contains(char::is_alphabetic);
+
+        let mut rule = match non_totalistic {
+            true => {
+                if neighbourhood != Neighbourhood::Moore {
+                    return Err(String::from("Hensel letters only work with the Moore neighbourhood"));
+                }
+                Rule {
+                    name: String::new(),
+                    birth: configurations(&birth)?,
+                    survival: configurations(&survival)?,
+                    states,
+                    neighbourhood,
+                    non_totalistic,
+                    table: None,
+                    colours: 0,
+                }
+            },
+            false => {
+                let max_count = neighbourhood.max_count();
+                Rule {
+                    name: String::new(),
+                    birth: counts(&birth, max_count)?,
+                    survival: counts(&survival, max_count)?,
+                    states,
+                    neighbourhood,
+                    non_totalistic,
+                    table: None,
+                    colours: 0,
+                }
+            },
+        };
+        rule.simplify();
+        rule.name = rule.write();
+        Ok(rule)
+    }
+
+    // Letters that pick every class of a count (`B3aceijknqry...`) are the
+    // same as no letters, keep those rules as plain counts.
+    fn simplify(&mut self) {
+        if !self.non_totalistic {
+            return;
+        }
+        let uniform = |table: &[bool]| {
+            (0..=8).all(|count| {
+                let mut on = (0..256).filter(|config: &usize| config.count_ones() == count).map(|config| table[config]);
+                let first = on.next().unwrap_or(false);
+                on.all(|value| value == first)
+            })
+        };
+        if !uniform(&self.birth) || !uniform(&self.survival) {
+            return;
+        }
+        let by_count = |table: &[bool]| -> Vec<bool> {
+            (0..=8).map(|count| table[(1usize << count) - 1]).collect()
+        };
+        self.birth = by_count(&self.birth);
+        self.survival = by_count(&self.survival);
+        self.non_totalistic = false;
+    }
+
+    fn parse_larger_than_life(text: &str) -> Result<Rule, String> {
+        let mut radius: Option<u32> = None;
+        let mut states = 2;
+        let mut middle = false;
+        let mut shape = RangeShape::Moore;
+        let mut birth = Vec::<(u32, u32)>::new();
+        let mut survival = Vec::<(u32, u32)>::new();
+        // S and B can have more than one range, e.g. S2..3,5..6
+        let mut last_list = ' ';
+
+        for part in text.split(',') {
+            let part = part.trim();
+            let (key, value) = match part.chars().next() {
+                Some(key) if key.is_ascii_alphabetic() => (key, &part[1..]),
+                Some(_) => (last_list, part),
+                None => continue,
+            };
+            let number = || value.parse::<u32>().map_err(|_| format!("`{part}` needs a number"));
+
+            match key {
+                'R' => radius = Some(number()?),
+                'C' => states = number()?.max(2),
+                'M' => middle = number()? == 1,
+                'S' | 'B' => {
+                    let (low, high) = match value.split_once("..") {
+                        Some((low, high)) => (low.parse::<u32>(), high.parse::<u32>()),
+                        None => (value.parse::<u32>(), value.parse::<u32>()),
+                    };
+                    let range = match (low, high) {
+                        (Ok(low), Ok(high)) if low <= high => (low, high),
+                        _ => return Err(format!("`{part}` is not a range like 34..58")),
+                    };
+                    match key {
+                        'S' => survival.push(range),
+                        _ => birth.push(range),
+                    }
+                    last_list = key;
+                },
+                'N' => {
+                    shape = match value {
+                        "M" => RangeShape::Moore,
+                        "N" => RangeShape::VonNeumann,
+                        "C" => RangeShape::Circular,
+                        _ => return Err(format!("unknown neighbourhood `{value}`, use NM, NN or NC")),
+                    }
+                },
+                _ => return Err(format!("don't know what `{part}` is")),
+            }
+        }
+
+        let radius = match radius {
+            Some(radius) if (1..=MAX_RADIUS).contains(&radius) => radius,
+            _ => return Err(format!("the radius must be 1 to {MAX_RADIUS}")),
+        };
+        if states > MAX_STATES {
+            return Err(format!("the number of states must be 2 to {MAX_STATES}"));
+        }
+
+        let neighbourhood = Neighbourhood::Range { radius, shape, middle };
+        let max_count = neighbourhood.max_count();
+        let ranges = |ranges: &[(u32, u32)]| -> Result<Vec<bool>, String> {
+            let mut counts = vec![false; max_count as usize + 1];
+            for (low, high) in ranges {
+                if *high > max_count {
+                    return Err(format!("{low}..{high} is past the {max_count} neighbours there are"));
+                }
+                for count in *low..=*high {
+                    counts[count as usize] = true;
+                }
+            }
+            Ok(counts)
+        };
+
+        let mut rule = Rule {
+            name: String::new(),
+            birth: ranges(&birth)?,
+            survival: ranges(&survival)?,
+            states: states as u8,
+            neighbourhood,
+            non_totalistic: false,
+            table: None,
+            colours: 0,
+        };
+        rule.name = rule.write();
+        Ok(rule)
+    }
+
+    fn write(&self) -> String {
+        let rulestring = self.write_rulestring();
+        match COLOUR_RULES.iter().find(|(_, colours)| *colours == self.colours) {
+            Some((word, _)) if rulestring == "B3/S23" => word.to_string(),
+            Some((word, _)) => format!("{rulestring} {word}"),
+            None => rulestring,
+        }
+    }
+
+    fn write_rulestring(&self) -> String {
+        if let Neighbourhood::Range { radius, shape, middle } = &self.neighbourhood {
+            let states = match self.states {
+                2 => 0,
+                states => states,
+            };
+            let shape = match shape {
+                RangeShape::Moore => "M",
+                RangeShape::VonNeumann => "N",
+                RangeShape::Circular => "C",
+            };
+            return format!(
+                "R{radius},C{states},M{},S{},B{},N{shape}",
+                *middle as u8,
+                write_ranges(&self.survival),
+                write_ranges(&self.birth)
+            );
+        }
+
+        let list = |counts: &[bool]| -> String {
+            let numbers: Vec<String> = (0..counts.len())
+                .filter(|n| counts[*n])
+                .map(|n| n.to_string())
+                .collect();
+            // Digits one after the other, unless counts can have two.
+            match counts.len() > 10 {
+                true => numbers.join(","),
+                false => numbers.concat(),
+            }
+        };
+
+        let mut name = match self.non_totalistic {
+            true => format!("B{}/S{}", write_hensel(&self.birth), write_hensel(&self.survival)),
+            false => format!("B{}/S{}", list(&self.birth), list(&self.survival)),
+        };
+        if self.states > 2 {
+            name.push_str(&format!("/C{}", self.states));
+        }
+        name.push_str(&self.neighbourhood.write());
+        name
+    }
+
+    pub fn is_life(&self) -> bool {
+        self.name == "B3/S23"
+    }
+
+    // Hexagonal rules shift odd rows half a cell, so on a torus the row
+    // after the last one must be odd again. That needs an even number of rows.
+    pub fn fits(&self, rows: usize) -> Result<(), String> {
+        if self.neighbourhood == Neighbourhood::Hexagonal && rows % 2 == 1 {
+            return Err(format!("hexagonal rules need an even number of rows, the map has {rows}"));
+        }
+        Ok(())
+    }
+
+    pub fn born(&self, neighbors: u32) -> bool {
+        self.birth.get(neighbors as usize).copied().unwrap_or(false)
+    }
+
+    pub fn survives(&self, neighbors: u32) -> bool {
+        self.survival.get(neighbors as usize).copied().unwrap_or(false)
+    }
+
+    // Colour of a state, only rule tables have them.
+    pub fn colour(&self, state: u8) -> Option<(u8, u8, u8)> {
+        self.table.as_ref().and_then(|table| table.colour(state))
+    }
+
+    pub fn describe(&self) -> String {
+        if let Some(table) = &self.table {
+            return format!("{} (rule table, {} states)", table.name, self.states);
+        }
+        if self.colours > 0 {
+            return format!("{} ({} colours)", self.name, self.colours);
+        }
+        let mut description = self.name.clone();
+        if self.non_totalistic {
+            description.push_str(" (non-totalistic)");
+        }
+        if self.states > 2 {
+            description.push_str(&format!(" (Generations, {} states)", self.states));
+        }
+        description
+    }
+}
+
+// [false, true, true, false, true] -> "1..2,4..4"
+fn write_ranges(counts: &[bool]) -> String {
+    let mut ranges = Vec::<String>::new();
+    let mut start: Option<usize> = None;
+    for count in 0..=counts.len() {
+        let on = counts.get(count).copied().unwrap_or(false);
+        match (on, start) {
+            (true, None) => start = Some(count),
+            (false, Some(low)) => {
+                ranges.push(format!("{low}..{}", count - 1));
+                start = None;
+            },
+            _ => (),
+        }
+    }
+    ranges.join(",")
+}
+
+// "23" -> 2 and 3 are in the list. With commas, "2,10" -> 2 and 10.
+fn counts(text: &str, max_count: u32) -> Result<Vec<bool>, String> {
+    let numbers: Vec<&str> = match max_count >= 10 {
+        true => text.split(',').filter(|number| !number.is_empty()).collect(),
+        false => text.matches(|_| true).collect(),
+    };
+
+    let mut counts = vec![false; max_count as usize + 1];
+    for number in numbers {
+        match number.parse::<u32>() {
+            Ok(n) if n <= max_count => counts[n as usize] = true,
+            _ => return Err(format!("`{number}` is not a number of neighbours (0 to {max_count})")),
+        }
+    }
+    Ok(counts)
+}
+
+// Hensel letter of a configuration of the 8 neighbours, None for 0 and 8
+// neighbours that have only one way.
+fn hensel_letter(config: usize) -> Option<char> {
+    // 5 to 7 neighbours: the letter of the dead ones.
+    let config = match config.count_ones() {
+        0 | 8 => return None,
+        1..=4 => config,
+        _ => !config & 0xff,
+    };
+    let class = symmetric_class(config);
+    HENSEL
+        .iter()
+        .find(|(_, cells)| {
+            let cells = cells.chars().enumerate().filter(|(_, ch)| *ch == '1').map(|(n, _)| 1 << n).sum();
+            symmetric_class(cells) == class
+        })
+        .map(|(letter, _)| *letter)
+}
+
+// Smallest of the 8 rotations and reflections of a configuration, the same
+// for every configuration of a class.
+fn symmetric_class(config: usize) -> usize {
+    // A quarter turn moves every neighbour 2 places around the ring, and
+    // flipping left to right swaps ring places n and 8 - n.
+    let rotate = |config: usize| ((config << 2) | (config >> 6)) & 0xff;
+    let flip = |config: usize| (0..8).filter(|n| config & (1 << n) != 0).map(|n| 1 << ((8 - n) % 8)).sum();
+
+    let mut smallest = config;
+    for mut turned in [config, flip(config)] {
+        for _ in 0..4 {
+            smallest = smallest.min(turned);
+            turned = rotate(turned);
+        }
+    }
+    smallest
+}
+
+// "2-a3" -> every configuration of 2 neighbours but 2a, and every one of 3.
+fn configurations(text: &str) -> Result<Vec<bool>, String> {
+    let letters: Vec<Option<char>> = (0..256).map(hensel_letter).collect();
+    let mut table = vec![false; 256];
+
+    let mut chars = text.chars().peekable();
+    while let Some(digit) = chars.next() {
+        let count = match digit.to_digit(10) {
+            Some(count) if count <= 8 => count,
+            _ => return Err(format!("`{digit}` is not a number of neighbours (0 to 8)")),
+        };
+        let exclude = chars.next_if_eq(&'-').is_some();
+        let mut chosen = String::new();
+        while let Some(letter) = chars.next_if(|ch| ch.is_alphabetic()) {
+            chosen.push(letter);
+        }
+
+        for letter in chosen.chars() {
+            let exists = (0..256).any(|config: usize| config.count_ones() == count && letters[config] == Some(letter));
+            if !exists {
+                return Err(format!("{count}{letter} is not a Hensel class"));
+            }
+        }
+        for config in (0..256).filter(|config: &usize| config.count_ones() == count) {
+            let picked = match letters[config] {
+                Some(letter) => chosen.is_empty() || chosen.contains(letter) != exclude,
+                None => true,
+            };
+            if picked {
+                table[config] = true;
+            }
+        }
+    }
+    Ok(table)
+}
+
+// The shortest way to write a table of configurations, `2-a` rather than
+// `2ceikn`.
+fn write_hensel(table: &[bool]) -> String {
+    let mut text = String::new();
+    for count in 0..=8 {
+        let configs: Vec<usize> = (0..256).filter(|config: &usize| config.count_ones() == count).collect();
+        let mut on = String::new();
+        let mut off = String::new();
+        for letter in HENSEL_LETTERS.chars() {
+            let class: Vec<usize> = configs.iter().copied().filter(|config| hensel_letter(*config) == Some(letter)).collect();
+            match class.first() {
+                Some(config) if table[*config] => on.push(letter),
+                Some(_) => off.push(letter),
+                None => (),
+            }
+        }
+
+        if count == 0 || count == 8 {
+            if table[configs[0]] {
+                text.push_str(&count.to_string());
+            }
+        } else if off.is_empty() {
+            text.push_str(&count.to_string());
+        } else if !on.is_empty() {
+            match off.len() < on.len() {
+                true => text.push_str(&format!("{count}-{off}")),
+                false => text.push_str(&format!("{count}{on}")),
+            }
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{next_generation, Cell, Vectrix};
+
+    fn name_of(text: &str) -> String {
+        Rule::parse(text).unwrap().name
+    }
+
+    #[test]
+    fn rules_in_any_notation() {
+        assert_eq!(name_of("B3/S23"), "B3/S23");
+        assert_eq!(name_of("23/3"), "B3/S23");
+        assert_eq!(name_of(" b36/s23 "), "B36/S23");
+        assert_eq!(name_of("S23/B3"), "B3/S23");
+        assert!(Rule::parse("23/3").unwrap().is_life());
+        assert!(!Rule::parse("B36/S23").unwrap().is_life());
+    }
+
+    #[test]
+    fn generations_rules() {
+        let brain = Rule::parse("B2/S/C3").unwrap();
+        assert_eq!((brain.name.as_str(), brain.states), ("B2/S/C3", 3));
+        assert_eq!(brain.describe(), "B2/S/C3 (Generations, 3 states)");
+
+        let star_wars = Rule::parse("345/2/4").unwrap();
+        assert_eq!((star_wars.name.as_str(), star_wars.states), ("B2/S345/C4", 4));
+        assert!(star_wars.born(2) && !star_wars.born(3));
+        assert!(star_wars.survives(5) && !star_wars.survives(2));
+    }
+
+    #[test]
+    fn bad_rules_are_errors() {
+        for text in ["B9/S23", "B3/S23/C1", "B3/S23/C256", "X3/S23", "1/2/3/4", "B3/S2x"] {
+            assert!(Rule::parse(text).is_err(), "{text}");
+        }
+    }
+
+    #[test]
+    fn neighbourhood_offsets() {
+        assert_eq!(Neighbourhood::Moore.offsets(false).len(), 8);
+        assert_eq!(Neighbourhood::VonNeumann.offsets(false), [(-1, 0, 1), (0, -1, 1), (0, 1, 1), (1, 0, 1)]);
+
+        // Odd rows lean right, even rows lean left.
+        let odd = Neighbourhood::Hexagonal.offsets(true);
+        let even = Neighbourhood::Hexagonal.offsets(false);
+        assert!(odd.contains(&(-1, 1, 1)) && !odd.contains(&(-1, -1, 1)));
+        assert!(even.contains(&(1, -1, 1)) && !even.contains(&(1, 1, 1)));
+        assert_eq!((odd.len(), even.len()), (6, 6));
+    }
+
+    #[test]
+    fn neighbourhood_letters_and_masks() {
+        assert_eq!(name_of("b2/s34v"), "B2/S34V");
+        assert_eq!(name_of("B2/S34H"), "B2/S34H");
+
+        // Weights add up to 12, so counts are split by commas.
+        let weighted = Rule::parse("B3,4/S2,3,4/N121,202,121").unwrap();
+        assert_eq!(weighted.name, "B3,4/S2,3,4/N121,202,121");
+        assert!(weighted.born(4) && weighted.survives(2) && !weighted.born(12));
+        assert_eq!(weighted.neighbourhood.offsets(false).iter().map(|(_, _, weight)| weight).sum::<u32>(), 12);
+
+        assert!(Rule::parse("B3/S23/N11,11").is_err());
+        assert!(Rule::parse("B3/S23/N111,1x1,111").is_err());
+        assert!(Rule::parse("B5/S2V").is_err());
+    }
+
+    #[test]
+    fn larger_than_life_rules() {
+        let bosco = Rule::parse("R5,C0,M1,S34..58,B34..45,NM").unwrap();
+        assert_eq!(bosco.name, "R5,C0,M1,S34..58,B34..45,NM");
+        assert_eq!(name_of(&bosco.name), bosco.name);
+        assert!(bosco.neighbourhood == Neighbourhood::Range { radius: 5, shape: RangeShape::Moore, middle: true });
+        assert!(bosco.survives(34) && bosco.survives(58) && !bosco.survives(59));
+        assert!(bosco.born(45) && !bosco.born(33));
+
+        // More than one range, dying states and a circle.
+        let rule = Rule::parse("r2,c3,m0,s2..3,5..6,b4,nc").unwrap();
+        assert_eq!(rule.name, "R2,C3,M0,S2..3,5..6,B4..4,NC");
+        assert_eq!(name_of(&rule.name), rule.name);
+
+        assert!(Rule::parse("R0,C0,M1,S1..2,B3..3,NM").is_err());
+        assert!(Rule::parse("R1,C0,M1,S1..10,B3..3,NM").is_err());
+        assert!(Rule::parse("R1,C0,M1,S3..2,B3..3,NM").is_err());
+        assert!(Rule::parse("R1,C0,M1,S1..2,B3..3,NX").is_err());
+    }
+
+    #[test]
+    fn fifty_one_hensel_classes() {
+        let mut classes = Vec::<usize>::new();
+        let mut letters = Vec::<usize>::new();
+        for count in 0..=8 {
+            let configs: Vec<usize> = (0..256).filter(|config: &usize| config.count_ones() == count).collect();
+            let mut class_list: Vec<usize> = configs.iter().map(|config| symmetric_class(*config)).collect();
+            class_list.sort();
+            class_list.dedup();
+            classes.push(class_list.len());
+
+            // Every class of a count has its own letter.
+            let mut letter_list: Vec<char> = configs.iter().filter_map(|config| hensel_letter(*config)).collect();
+            letter_list.sort();
+            letter_list.dedup();
+            letters.push(letter_list.len());
+        }
+        assert_eq!(classes, [1, 2, 6, 10, 13, 10, 6, 2, 1]);
+        assert_eq!(classes.iter().sum::<usize>(), 51);
+        assert_eq!(letters, [0, 2, 6, 10, 13, 10, 6, 2, 0]);
+    }
+
+    #[test]
+    fn hensel_rules_read_back() {
+        for text in ["B2-a/S12", "B3/S2-i34q", "B2ce/S", "B36/S125-k"] {
+            let rule = Rule::parse(text).unwrap();
+            assert!(rule.non_totalistic, "{text}");
+            assert_eq!(rule.name, text);
+            assert_eq!(name_of(&rule.name), text);
+        }
+        // Every letter of a count is the same as the plain count.
+        assert_eq!(name_of("B3aceijknqry/S23"), "B3/S23");
+        assert_eq!(name_of("B2cekain/S"), "B2/S");
+        assert_eq!(Rule::parse("B2-a/S12").unwrap().describe(), "B2-a/S12 (non-totalistic)");
+    }
+
+    #[test]
+    fn bad_hensel_letters_are_errors() {
+        // 1 only has c and e, 2 has no z, 0 and 8 have no letters.
+        for text in ["B1a/S23", "B2z/S23", "B3/S0c", "B3/S8e", "B3/S2x", "B3/S23aV"] {
+            assert!(Rule::parse(text).is_err(), "{text}");
+        }
+    }
+
+    #[test]
+    fn blinker_dies_in_tlife() {
+        // The middle of a blinker has 2 neighbours on opposite sides, 2i,
+        // which tlife doesn't let survive. The ends die anyway and 3a
+        // gives births above and below the middle.
+        let mut map: Vectrix = vec![vec![Cell::dead(); 5]; 5];
+        map[2][1..4].fill(Cell::alive());
+        let tlife = Rule::parse("B3/S2-i34q").unwrap();
+
+        let alive = |map: &Vectrix| -> Vec<(usize, usize)> {
+            (0..5).flat_map(|i| (0..5).map(move |j| (i, j))).filter(|(i, j)| matches!(map[*i][*j], Cell::Alive(..))).collect()
+        };
+        let next = next_generation(&map, &tlife);
+        assert_eq!(alive(&next), [(1, 2), (3, 2)]);
+        assert!(alive(&next_generation(&next, &tlife)).is_empty());
+
+        // Under Life the middle survives.
+        assert_eq!(alive(&next_generation(&map, &Rule::life())), [(1, 2), (2, 2), (3, 2)]);
+    }
+
+    #[test]
+    fn births_by_letter() {
+        // The cells next to a diagonal pair see 2e, two sides at right angles.
+        let mut map: Vectrix = vec![vec![Cell::dead(); 6]; 6];
+        map[2][2] = Cell::alive();
+        map[3][3] = Cell::alive();
+        let flipped = |rule: &str| -> bool {
+            let next = next_generation(&map, &Rule::parse(rule).unwrap());
+            let population = next.iter().flatten().filter(|cell| matches!(cell, Cell::Alive(..))).count();
+            population == 2 && matches!(next[2][3], Cell::Alive(..)) && matches!(next[3][2], Cell::Alive(..))
+        };
+        assert!(flipped("B2/S"));
+        assert!(flipped("B2e/S"));
+        assert!(!flipped("B2-e/S"));
+        assert!(!flipped("B2a/S"));
+    }
+
+    #[test]
+    fn colour_rules() {
+        let immigration = Rule::parse("Immigration").unwrap();
+        assert_eq!((immigration.name.as_str(), immigration.colours), ("Immigration", 2));
+        assert!(!immigration.is_life());
+
+        let quad = Rule::parse("b36/s23 quadlife").unwrap();
+        assert_eq!((quad.name.as_str(), quad.colours), ("B36/S23 QuadLife", 4));
+        assert_eq!(name_of(&quad.name), quad.name);
+        assert_eq!(quad.describe(), "B36/S23 QuadLife (4 colours)");
+
+        assert!(Rule::parse("B2/S/C3 QuadLife").is_err());
+        assert!(Rule::parse("Immigration QuadLife").is_err());
+    }
+
+    #[test]
+    fn newborns_take_the_parents_colour() {
+        let mut map: Vectrix = vec![vec![Cell::dead(); 5]; 5];
+        map[2][1] = Cell::coloured(1);
+        map[2][2] = Cell::coloured(2);
+        map[2][3] = Cell::coloured(1);
+
+        // Two parents of colour 1 out of three, the middle keeps its colour.
+        let next = next_generation(&map, &Rule::parse("Immigration").unwrap());
+        assert_eq!((next[1][2].colour(), next[2][2].colour(), next[3][2].colour()), (1, 2, 1));
+
+        // Three parents of three colours give the fourth one.
+        map[2][3] = Cell::coloured(3);
+        let next = next_generation(&map, &Rule::parse("QuadLife").unwrap());
+        assert_eq!((next[1][2].colour(), next[2][2].colour(), next[3][2].colour()), (4, 2, 4));
+    }
+
+    #[test]
+    fn brians_brain_cells_die_slowly() {
+        // Two live cells side by side: they start dying and give birth to
+        // the cells above and below them, which only see each other.
+        let mut map: Vectrix = vec![vec![Cell::dead(); 6]; 6];
+        map[2][2] = Cell::alive();
+        map[2][3] = Cell::alive();
+        let brain = Rule::parse("B2/S/C3").unwrap();
+
+        let states = |map: &Vectrix| -> Vec<Vec<u8>> {
+            map.iter().map(|row| row.iter().map(|cell| cell.state()).collect()).collect()
+        };
+        let next = next_generation(&map, &brain);
+        let mut expected = vec![vec![0u8; 6]; 6];
+        expected[2][2] = 2;
+        expected[2][3] = 2;
+        expected[1][2] = 1;
+        expected[1][3] = 1;
+        expected[3][2] = 1;
+        expected[3][3] = 1;
+        assert_eq!(states(&next), expected);
+
+        // Dying cells are not neighbours, and go on to dead.
+        let after = states(&next_generation(&next, &brain));
+        assert_eq!((after[2][2], after[2][3]), (0, 0));
+        assert_eq!((after[1][2], after[3][3]), (2, 2));
+    }
+}
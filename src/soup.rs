@@ -0,0 +1,248 @@
+// Random soups.
+//
+// A soup fills a region of the map with random cells. The random numbers come
+// from a seed, so the same seed, density, region and symmetry always give the
+// same soup.
+
+use crate::clipboard::Selection;
+use crate::{Cell, Vectrix};
+
+// SplitMix64. Small, fast, and good enough for soups.
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    // A seed from the clock, for when the user doesn't give one.
+    pub fn clock_seed() -> u64 {
+        use std::time::{SystemTime, UNIX_EPOCH};
+
+        match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(time) => time.as_nanos() as u64,
+            Err(_) => 0,
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // Number in 0..bound
+    pub fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum Symmetry {
+    None,
+    // 180 degree rotation
+    C2,
+    // 90 degree rotation. Needs a square region.
+    C4,
+    // Mirrored left to right
+    D2,
+    // Mirrored left to right and top to bottom
+    D4,
+    // Every rotation and reflection of the square. Needs a square region.
+    D8,
+}
+
+impl Symmetry {
+    pub fn name(&self) -> &str {
+        match self {
+            Symmetry::None => "None",
+            Symmetry::C2 => "C2",
+            Symmetry::C4 => "C4",
+            Symmetry::D2 => "D2",
+            Symmetry::D4 => "D4",
+            Symmetry::D8 => "D8",
+        }
+    }
+
+    fn needs_square(&self) -> bool {
+        matches!(self, Symmetry::C4 | Symmetry::D8)
+    }
+
+    // Every cell that must match (i, j) in a region of `rows` x `cols`.
+    fn orbit(&self, i: usize, j: usize, rows: usize, cols: usize) -> Vec<(usize, usize)> {
+        let (i_rev, j_rev) = (rows - 1 - i, cols - 1 - j);
+
+        match self {
+            Symmetry::None => vec![(i, j)],
+            Symmetry::C2 => vec![(i, j), (i_rev, j_rev)],
+            // Square, so rows == cols.
+            Symmetry::C4 => vec![(i, j), (j, i_rev), (i_rev, j_rev), (j_rev, i)],
+            Symmetry::D2 => vec![(i, j), (i, j_rev)],
+            Symmetry::D4 => vec![(i, j), (i, j_rev), (i_rev, j), (i_rev, j_rev)],
+            Symmetry::D8 => vec![
+                (i, j), (j, i_rev), (i_rev, j_rev), (j_rev, i),
+                (i, j_rev), (i_rev, j), (j, i), (j_rev, i_rev),
+            ],
+        }
+    }
+}
+
+pub struct Soup {
+    pub seed: u64,
+    // Percentage of live cells, 0 to 100.
+    pub density: u32,
+    pub symmetry: Symmetry,
+    // Immigration and QuadLife: every live cell gets one of these colours at
+    // random. Empty for plain live cells.
+    pub colours: Vec<u8>,
+}
+
+impl Soup {
+    // Fill the region with the soup. Every cell of the region is overwritten.
+    // Returns the message with the settings, seed included, to repeat it later.
+    pub fn fill(&self, map: &mut Vectrix, region: &Selection) -> String {
+        let mut rows = region.rows();
+        let mut cols = region.cols();
+        if self.symmetry.needs_square() {
+            rows = rows.min(cols);
+            cols = rows;
+        }
+
+        let mut rng = Rng::new(self.seed);
+        let mut soup = vec![vec![Cell::dead(); cols]; rows];
+
+        // Row by row, the smallest cell of each orbit is visited first.
+        // That one is random, the rest of the orbit copies it.
+        for i in 0..rows {
+            for j in 0..cols {
+                let orbit = self.symmetry.orbit(i, j, rows, cols);
+                let (i_first, j_first) = *orbit.iter().min().unwrap();
+                soup[i][j] = if (i_first, j_first) == (i, j) {
+                    match rng.below(100) < self.density as u64 {
+                        true => self.live_cell(&mut rng),
+                        false => Cell::dead(),
+                    }
+                } else {
+                    soup[i_first][j_first].clone()
+                };
+            }
+        }
+
+        for (i, soup_row) in soup.into_iter().enumerate() {
+            for (j, cell) in soup_row.into_iter().enumerate() {
+                map[region.top + i][region.left + j] = cell;
+            }
+        }
+
+        format!(
+            "[+] Random fill: {}% density, {} symmetry, {} x {} region, seed {}",
+            self.density,
+            self.symmetry.name(),
+            rows,
+            cols,
+            self.seed
+        )
+    }
+
+    // Plain, or one of the soup's colours. A single colour takes no random
+    // number, so plain soups stay the same for the same seed.
+    fn live_cell(&self, rng: &mut Rng) -> Cell {
+        match self.colours.len() {
+            0 => Cell::alive(),
+            1 => Cell::coloured(self.colours[0]),
+            count => Cell::coloured(self.colours[rng.below(count as u64) as usize]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Coordinates;
+
+    fn soup(seed: u64, density: u32, symmetry: Symmetry) -> Soup {
+        Soup { seed, density, symmetry, colours: vec![] }
+    }
+
+    // Filled `rows` x `cols` map, true for live cells.
+    fn filled(soup: &Soup, rows: usize, cols: usize) -> Vec<Vec<bool>> {
+        let mut map = vec![vec![Cell::dead(); cols]; rows];
+        let region = Selection::new(&map, Coordinates::new(0, 0), Coordinates::new(rows - 1, cols - 1));
+        soup.fill(&mut map, &region);
+        map.iter().map(|row| row.iter().map(|cell| matches!(cell, Cell::Alive(..))).collect()).collect()
+    }
+
+    #[test]
+    fn same_seed_same_soup() {
+        let first = filled(&soup(42, 50, Symmetry::None), 12, 20);
+        assert_eq!(first, filled(&soup(42, 50, Symmetry::None), 12, 20));
+        assert_ne!(first, filled(&soup(43, 50, Symmetry::None), 12, 20));
+
+        let alive = first.iter().flatten().filter(|alive| **alive).count();
+        assert!((60..180).contains(&alive), "{alive} of 240 alive at 50%");
+        assert!(filled(&soup(1, 0, Symmetry::None), 5, 5).iter().flatten().all(|alive| !alive));
+        assert!(filled(&soup(1, 100, Symmetry::None), 5, 5).iter().flatten().all(|alive| *alive));
+    }
+
+    #[test]
+    fn symmetries_are_symmetric() {
+        let n = 10;
+        for seed in 0..5 {
+            let c2 = filled(&soup(seed, 40, Symmetry::C2), 8, 13);
+            let (rows, cols) = (c2.len(), c2[0].len());
+            let d2 = filled(&soup(seed, 40, Symmetry::D2), 8, 13);
+            let d4 = filled(&soup(seed, 40, Symmetry::D4), 8, 13);
+            for i in 0..rows {
+                for j in 0..cols {
+                    assert_eq!(c2[i][j], c2[rows - 1 - i][cols - 1 - j]);
+                    assert_eq!(d2[i][j], d2[i][cols - 1 - j]);
+                    assert_eq!(d4[i][j], d4[rows - 1 - i][j]);
+                    assert_eq!(d4[i][j], d4[i][cols - 1 - j]);
+                }
+            }
+
+            let c4 = filled(&soup(seed, 40, Symmetry::C4), n, n);
+            let d8 = filled(&soup(seed, 40, Symmetry::D8), n, n);
+            for i in 0..n {
+                for j in 0..n {
+                    // A quarter turn.
+                    assert_eq!(c4[i][j], c4[j][n - 1 - i]);
+                    assert_eq!(d8[i][j], d8[j][n - 1 - i]);
+                    assert_eq!(d8[i][j], d8[j][i]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn square_symmetries_use_a_square() {
+        let mut map = vec![vec![Cell::alive(); 9]; 4];
+        let region = Selection::new(&map, Coordinates::new(0, 0), Coordinates::new(3, 8));
+        let message = soup(7, 0, Symmetry::D8).fill(&mut map, &region);
+        assert!(message.contains("4 x 4 region"), "{message}");
+        // Cells right of the square are left alone.
+        assert!(matches!(map[0][8], Cell::Alive(..)));
+        assert!(matches!(map[0][3], Cell::Dead(_)));
+    }
+
+    #[test]
+    fn coloured_soups_keep_their_symmetry() {
+        let mut quad = soup(9, 60, Symmetry::C2);
+        quad.colours = vec![1, 2, 3, 4];
+        let mut map = vec![vec![Cell::dead(); 10]; 8];
+        let region = Selection::new(&map, Coordinates::new(0, 0), Coordinates::new(7, 9));
+        quad.fill(&mut map, &region);
+
+        let colours: Vec<u8> = map.iter().flatten().map(|cell| cell.colour()).collect();
+        assert!((1..=4).all(|colour| colours.contains(&colour)), "{colours:?}");
+        for i in 0..8 {
+            for j in 0..10 {
+                assert_eq!(map[i][j].colour(), map[7 - i][9 - j].colour());
+            }
+        }
+    }
+}
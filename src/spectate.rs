@@ -0,0 +1,330 @@
+// Spectators: other computers watching this one play, without changing
+// anything.
+//
+// They connect to the port opened in the configuration, see net.rs for the
+// messages. A newcomer gets the whole map at once, then only the cells that
+// changed in every generation. Anything else they send is ignored, except
+// asking for the whole map again. A thread of its own answers newcomers, so
+// they get the map even while this computer waits in a prompt or plays
+// Versus.
+//
+// Watching has its own viewport, so spectators can move around and zoom
+// while the game goes on.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::net::{self, Message, Session, SessionEvent};
+use crate::{
+    Density, MENU_LINES, Neighbourhood, Rule, Vectrix, Viewport, clear_console, fit_line, map_text, network_cell,
+    print_header, print_map_viewport, print_message, read_line, received_map,
+};
+
+// How far w/a/s/d move without a count.
+const STEP: usize = 5;
+// How often newcomers are answered.
+const ANSWER_INTERVAL: Duration = Duration::from_millis(100);
+
+pub struct Spectators {
+    // Shared with the thread answering newcomers.
+    watched: Arc<Mutex<Watched>>,
+    stopped: Arc<AtomicBool>,
+}
+
+struct Watched {
+    session: Session,
+    // What they see now.
+    shown: Vectrix,
+    rule: String,
+    generation: u32,
+}
+
+impl Spectators {
+    pub fn open(port: u16) -> Result<Spectators, String> {
+        let watched = Arc::new(Mutex::new(Watched {
+            session: Session::host(port)?,
+            shown: Vec::new(),
+            rule: String::new(),
+            generation: 0,
+        }));
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        let (answering, stop) = (watched.clone(), stopped.clone());
+        std::thread::spawn(move || {
+            while !stop.load(Ordering::Relaxed) {
+                if let Ok(mut watched) = answering.lock() {
+                    watched.answer();
+                }
+                std::thread::sleep(ANSWER_INTERVAL);
+            }
+        });
+        Ok(Spectators { watched, stopped })
+    }
+
+    pub fn count(&self) -> usize {
+        self.watched.lock().map_or(0, |watched| watched.session.peer_names().len())
+    }
+
+    // Sends the map if it changed. Call it after anything that can change it.
+    pub fn serve(&mut self, map: &Vectrix, rule: &Rule) {
+        if let Ok(mut watched) = self.watched.lock() {
+            let generation = watched.generation;
+            watched.show(generation, map, rule);
+        }
+    }
+
+    // `map` is at `generation` now. The next generation only sends the cells
+    // that changed, anything else sends the whole map.
+    pub fn show(&mut self, generation: u32, map: &Vectrix, rule: &Rule) {
+        if let Ok(mut watched) = self.watched.lock() {
+            watched.show(generation, map, rule);
+        }
+    }
+
+    pub fn close(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        if let Ok(mut watched) = self.watched.lock() {
+            watched.session.close();
+        }
+    }
+}
+
+impl Watched {
+    // Newcomers, and spectators that missed something, get what the others
+    // see.
+    fn answer(&mut self) {
+        for event in self.session.poll() {
+            if let SessionEvent::Joined(peer, _) | SessionEvent::Received(peer, Message::Resync) = event
+                && !self.shown.is_empty()
+            {
+                let snapshot = Message::Snapshot {
+                    generation: self.generation,
+                    map: self.snapshot_text(),
+                };
+                self.session.send(peer, &snapshot);
+            }
+        }
+    }
+
+    fn show(&mut self, generation: u32, map: &Vectrix, rule: &Rule) {
+        // The update below follows what newcomers get.
+        self.answer();
+
+        let same_rule = rule.name == self.rule;
+        if same_rule && generation == self.generation && *map == self.shown {
+            return;
+        }
+        let same_size = map.len() == self.shown.len() && map[0].len() == self.shown.first().map_or(0, |row| row.len());
+        let update = match same_rule && same_size && generation == self.generation + 1 {
+            true => Message::Delta {
+                generation,
+                cells: net::changes(&self.shown, map),
+            },
+            false => Message::Snapshot {
+                generation,
+                map: map_text(map, rule),
+            },
+        };
+        self.session.broadcast(&update);
+
+        self.shown = map.clone();
+        self.rule = rule.name.clone();
+        self.generation = generation;
+    }
+
+    // The shown map in the map.txt format. Its rule was fine when it was shown.
+    fn snapshot_text(&self) -> String {
+        let rule = Rule::parse(&self.rule).unwrap_or_else(|_| Rule::life());
+        map_text(&self.shown, &rule)
+    }
+}
+
+// Watches the game of the host `session` is connected to, until the host
+// leaves or `q` is typed.
+pub fn watch(session: &mut Session) -> String {
+    let mut map: Vectrix = Vec::new();
+    let mut rule = Rule::life();
+    let mut generation: u32 = 0;
+    // Waiting for the whole map after missing something.
+    let mut resyncing = false;
+
+    // None fits the whole map like `play` does. Otherwise the layout and the
+    // top left cell picked by the spectator.
+    let mut zoom: Option<Density> = None;
+    let (mut top, mut left) = (0, 0);
+    let mut density = Density::Full;
+
+    let mut message = format!("[+] {}", session.describe());
+    let mut redraw = true;
+
+    loop {
+        for event in session.poll() {
+            match event {
+                SessionEvent::Closed(reason) => return format!("[-] Stopped watching: {reason}."),
+                SessionEvent::Received(_, Message::Snapshot { generation: received, map: text }) => {
+                    match received_map(&text) {
+                        Ok((received_map, received_rule)) => {
+                            map = received_map;
+                            rule = received_rule;
+                            generation = received;
+                            resyncing = false;
+                        },
+                        // E.g. a .rule file only the host has. Generations
+                        // are ignored until another map comes.
+                        Err(error) => {
+                            resyncing = true;
+                            message = format!("[-] Can't use the host's map: {error}.");
+                        },
+                    }
+                    redraw = true;
+                },
+                SessionEvent::Received(_, Message::Delta { generation: received, cells }) if !resyncing => {
+                    let fits = !map.is_empty()
+                        && received == generation + 1
+                        && cells.iter().all(|(row, col, _, _)| *row < map.len() && *col < map[0].len());
+                    match fits {
+                        true => {
+                            for (row, col, state, colour) in cells {
+                                map[row][col] = network_cell(state, colour, &rule);
+                            }
+                            generation = received;
+                        },
+                        // Missed something, e.g. joined while a generation was
+                        // on its way.
+                        false => {
+                            resyncing = true;
+                            session.broadcast(&Message::Resync);
+                            message = String::from("[-] Out of sync, getting the map again.");
+                        },
+                    }
+                    redraw = true;
+                },
+                _ => (),
+            }
+        }
+
+        if redraw {
+            clear_console();
+            print_header(vec!["Game of Life", "Watching"]);
+            match map.is_empty() {
+                true => print_message(&String::from("Waiting for the map..."), true),
+                false => {
+                    let mut viewport = match zoom {
+                        Some(zoom) => Viewport::zoomed(&map, zoom, top, left, MENU_LINES),
+                        None => Viewport::fit(&map, MENU_LINES, 0),
+                    };
+                    viewport.hex = rule.neighbourhood == Neighbourhood::Hexagonal && viewport.density == Density::Full;
+                    print_map_viewport(&map, &viewport, false, false);
+                    (top, left, density) = (viewport.top, viewport.left, viewport.density);
+                },
+            }
+            print_message(&session.describe(), true);
+            print_message(&fit_line(&format!("Generation {generation} | {}", rule.describe())), true);
+            print_message(&message, true);
+            print_message(&String::from("w/a/s/d [count]: move | z: zoom | f: fit | q: stop watching"), true);
+            print_message(&String::from("Command:"), true);
+            redraw = false;
+        }
+
+        // Checks the network again every 100 ms while waiting for a command.
+        let Some(line) = read_line(Some(std::time::Duration::from_millis(100))) else {
+            continue;
+        };
+        redraw = true;
+        message = String::new();
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let count = match words.get(1).map(|word| word.parse::<usize>()) {
+            Some(Ok(count)) => count,
+            Some(Err(_)) => {
+                message = String::from("[-] Unknown command.");
+                continue;
+            },
+            None => STEP,
+        };
+        match words.first().copied() {
+            Some(direction @ ("w" | "a" | "s" | "d")) => {
+                // Moving keeps the layout that was on screen.
+                zoom = Some(density);
+                match direction {
+                    "w" => top = top.saturating_sub(count),
+                    "s" => top = top.saturating_add(count),
+                    "a" => left = left.saturating_sub(count),
+                    _ => left = left.saturating_add(count),
+                }
+            },
+            Some("z") => {
+                density = match density {
+                    Density::Full => Density::Compact,
+                    Density::Compact => Density::HalfBlock,
+                    Density::HalfBlock => Density::Full,
+                };
+                zoom = Some(density);
+            },
+            Some("f") => {
+                zoom = None;
+                (top, left) = (0, 0);
+            },
+            Some("q") => {
+                session.close();
+                return String::from("[+] Stopped watching.");
+            },
+            None => (),
+            _ => message = String::from("[-] Unknown command."),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Cell, next_generation};
+    use std::time::{Duration, Instant};
+
+    // The next message `session` gets.
+    fn next_message(session: &mut Session) -> Message {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            for event in session.poll() {
+                if let SessionEvent::Received(_, message) = event {
+                    return message;
+                }
+            }
+            assert!(Instant::now() < deadline, "no message came");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn watchers_get_the_map_then_the_changes() {
+        let port = 47_911;
+        let mut spectators = Spectators::open(port).unwrap();
+        let mut map: Vectrix = vec![vec![Cell::dead(); 5]; 5];
+        map[2][1..4].fill(Cell::alive());
+        let rule = Rule::life();
+
+        spectators.show(0, &map, &rule);
+
+        // Nothing is shown while the watcher joins, like when the host is in
+        // a prompt. The map comes anyway.
+        let mut watcher = Session::join("127.0.0.1", port, "watcher").unwrap();
+
+        let (shown, _) = match next_message(&mut watcher) {
+            Message::Snapshot { generation: 0, map: text } => received_map(&text).unwrap(),
+            other => panic!("expected a snapshot, got {other:?}"),
+        };
+        assert!(shown == map);
+
+        // One generation later only the blinker's four changed cells are sent.
+        let next = next_generation(&map, &rule);
+        spectators.show(1, &next, &rule);
+        match next_message(&mut watcher) {
+            Message::Delta { generation: 1, cells } => assert_eq!(cells.len(), 4),
+            other => panic!("expected a delta, got {other:?}"),
+        }
+
+        watcher.close();
+        spectators.close();
+    }
+}
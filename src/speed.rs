@@ -0,0 +1,167 @@
+// Speed and direction of moving patterns.
+//
+// A pattern is run on the unbounded plane until it has its starting shape
+// again. The generations it took is the period, and how far it moved in that
+// time gives the speed. Speeds are written the usual way: `c` is one cell per
+// generation (the speed of light), so moving 2 cells in 4 generations is c/2
+// and moving 2 cells in 5 generations is 2c/5. Diagonal moves count the
+// cells along one axis, like a king in chess. Oblique moves give both axes,
+// larger first: 2 cells one way and 1 the other in 6 generations is (2,1)c/6.
+
+use crate::plane::Plane;
+
+// How long to wait for the pattern to repeat.
+pub const MAX_PERIOD: u32 = 1024;
+
+pub struct Velocity {
+    pub period: u32,
+    // (rows, cols) moved in one period.
+    pub shift: (i64, i64),
+}
+
+impl Velocity {
+    // None if the pattern doesn't repeat within `limit` generations.
+    pub fn measure(start: &Plane, limit: u32) -> Option<Velocity> {
+        if start.population() == 0 {
+            return None;
+        }
+        let shape = start.shape();
+        let (top, left) = start.corner();
+
+        let mut plane = start.clone();
+        for generation in 1..=limit {
+            plane = plane.step();
+            if plane.shape() == shape {
+                let (row, col) = plane.corner();
+                return Some(Velocity {
+                    period: generation,
+                    shift: (row - top, col - left),
+                });
+            }
+        }
+        None
+    }
+
+    pub fn is_moving(&self) -> bool {
+        self.shift != (0, 0)
+    }
+
+    // `c/4`, `2c/5`, `(2,1)c/6`...
+    pub fn speed(&self) -> String {
+        let (d_row, d_col) = (self.shift.0.unsigned_abs(), self.shift.1.unsigned_abs());
+        let (far, near) = (d_row.max(d_col), d_row.min(d_col));
+        let period = self.period as u64;
+
+        if self.direction() == "oblique" {
+            let divisor = gcd(gcd(far, near), period);
+            return format!("({},{})c/{}", far / divisor, near / divisor, period / divisor);
+        }
+        let divisor = gcd(far, period);
+        match far / divisor {
+            1 => format!("c/{}", period / divisor),
+            cells => format!("{cells}c/{}", period / divisor),
+        }
+    }
+
+    pub fn direction(&self) -> &str {
+        let (d_row, d_col) = (self.shift.0.abs(), self.shift.1.abs());
+        if d_row == 0 || d_col == 0 {
+            "orthogonal"
+        } else if d_row == d_col {
+            "diagonal"
+        } else {
+            "oblique"
+        }
+    }
+
+    // `c/4 diagonal`, as written in the pattern files.
+    pub fn notation(&self) -> String {
+        format!("{} {}", self.speed(), self.direction())
+    }
+
+    pub fn describe(&self) -> String {
+        if !self.is_moving() {
+            return match self.period {
+                1 => String::from("Still life."),
+                period => format!("Oscillator of period {period}."),
+            };
+        }
+        format!(
+            "Spaceship: {}, period {}, moves {:?} per period.",
+            self.notation(),
+            self.period,
+            self.shift
+        )
+    }
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    match b {
+        0 => a.max(1),
+        _ => gcd(b, a % b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A plane from rows like "x..", x is alive.
+    fn plane_of(rows: &[&str]) -> Plane {
+        let mut plane = Plane { cells: Default::default() };
+        for (i, row) in rows.iter().enumerate() {
+            for (j, ch) in row.chars().enumerate() {
+                if ch == 'x' {
+                    plane.cells.insert((i as i64, j as i64));
+                }
+            }
+        }
+        plane
+    }
+
+    #[test]
+    fn glider_speed() {
+        let glider = Velocity::measure(&plane_of(&[".x.", "..x", "xxx"]), MAX_PERIOD).unwrap();
+        assert_eq!((glider.period, glider.shift), (4, (1, 1)));
+        assert_eq!(glider.notation(), "c/4 diagonal");
+        assert_eq!(glider.describe(), "Spaceship: c/4 diagonal, period 4, moves (1, 1) per period.");
+    }
+
+    #[test]
+    fn lightweight_spaceship_speed() {
+        let lwss = Velocity::measure(&plane_of(&[".x..x", "x....", "x...x", "xxxx."]), MAX_PERIOD).unwrap();
+        assert_eq!((lwss.period, lwss.shift), (4, (0, -2)));
+        assert_eq!(lwss.notation(), "c/2 orthogonal");
+    }
+
+    #[test]
+    fn oscillators_stay_put() {
+        let blinker = Velocity::measure(&plane_of(&["xxx"]), MAX_PERIOD).unwrap();
+        assert!(!blinker.is_moving());
+        assert_eq!(blinker.describe(), "Oscillator of period 2.");
+
+        let block = Velocity::measure(&plane_of(&["xx", "xx"]), MAX_PERIOD).unwrap();
+        assert_eq!(block.describe(), "Still life.");
+    }
+
+    #[test]
+    fn speeds_are_reduced() {
+        assert_eq!(Velocity { period: 5, shift: (0, 2) }.speed(), "2c/5");
+        assert_eq!(Velocity { period: 6, shift: (-2, 0) }.speed(), "c/3");
+        assert_eq!(Velocity { period: 6, shift: (2, 1) }.direction(), "oblique");
+    }
+
+    #[test]
+    fn oblique_speeds_give_both_axes() {
+        assert_eq!(Velocity { period: 6, shift: (1, -2) }.notation(), "(2,1)c/6 oblique");
+        assert_eq!(Velocity { period: 12, shift: (-4, 2) }.speed(), "(2,1)c/6");
+        assert_eq!(Velocity { period: 7, shift: (3, 2) }.speed(), "(3,2)c/7");
+    }
+
+    #[test]
+    fn no_repeat_no_velocity() {
+        // An R-pentomino takes 1103 generations to settle.
+        assert!(Velocity::measure(&plane_of(&[".xx", "xx.", ".x."]), 100).is_none());
+        assert!(Velocity::measure(&plane_of(&["..."]), MAX_PERIOD).is_none());
+    }
+}
@@ -0,0 +1,209 @@
+// Statistics collected while playing.
+//
+// One entry per generation. They are shown in a panel next to the map during
+// the game, and can be saved as CSV afterwards. The heat map of the game is
+// kept here too.
+
+use crate::heatmap::HeatMap;
+use crate::{Cell, Vectrix};
+
+// Width of the side panel, in characters.
+pub const PANEL_COLS: usize = 26;
+
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+pub struct GenStats {
+    pub generation: u32,
+    pub population: usize,
+    // Cells born and died since the previous generation.
+    pub births: usize,
+    pub deaths: usize,
+    // (top, left, bottom, right) of the live cells. None if there are none.
+    // The map wraps, but the box doesn't: an object crossing an edge makes
+    // it as wide as the map.
+    pub bounding_box: Option<(usize, usize, usize, usize)>,
+    // Live cells over all the cells of the map, 0 to 1.
+    pub density: f64,
+}
+
+pub struct Statistics {
+    pub history: Vec<GenStats>,
+    pub heat_map: HeatMap,
+}
+
+impl Statistics {
+    pub fn new() -> Statistics {
+        Statistics {
+            history: Vec::new(),
+            heat_map: HeatMap::new(),
+        }
+    }
+
+    // `previous` is None for the first generation.
+    pub fn record(&mut self, generation: u32, previous: Option<&Vectrix>, map: &Vectrix) {
+        self.heat_map.record(previous, map);
+
+        let mut population = 0;
+        let mut births = 0;
+        let mut deaths = 0;
+        let mut bounding_box: Option<(usize, usize, usize, usize)> = None;
+
+        for (i, row) in map.iter().enumerate() {
+            for (j, cell) in row.iter().enumerate() {
+                let alive = matches!(cell, Cell::Alive(..));
+                let was_alive = match previous {
+                    Some(previous) => matches!(previous[i][j], Cell::Alive(..)),
+                    None => alive,
+                };
+
+                if alive && !was_alive {
+                    births += 1;
+                }
+                if !alive && was_alive {
+                    deaths += 1;
+                }
+                if alive {
+                    population += 1;
+                    bounding_box = Some(match bounding_box {
+                        Some((top, left, bottom, right)) => (top.min(i), left.min(j), bottom.max(i), right.max(j)),
+                        None => (i, j, i, j),
+                    });
+                }
+            }
+        }
+
+        self.history.push(GenStats {
+            generation,
+            population,
+            births,
+            deaths,
+            bounding_box,
+            density: population as f64 / (map.len() * map[0].len()) as f64,
+        });
+    }
+
+    // Lines of the side panel, for the last generation recorded.
+    pub fn panel(&self) -> Vec<String> {
+        let Some(last) = self.history.last() else {
+            return vec![];
+        };
+
+        let bounding_box = match last.bounding_box {
+            Some((top, left, bottom, right)) => format!(
+                "{} x {} at ({}, {})",
+                bottom - top + 1,
+                right - left + 1,
+                top,
+                left
+            ),
+            None => String::from("none"),
+        };
+
+        let lines = vec![
+            String::from("Statistics"),
+            format!("Population: {}", last.population),
+            format!("Births: {}  Deaths: {}", last.births, last.deaths),
+            format!("Box: {bounding_box}"),
+            format!("Density: {:.1}%", last.density * 100.0),
+            String::from("Population history:"),
+            self.sparkline(PANEL_COLS),
+        ];
+        // Big maps make long numbers. A wrapped line breaks the panel.
+        lines.into_iter().map(|line| line.chars().take(PANEL_COLS).collect()).collect()
+    }
+
+    // Last `width` populations, scaled to the highest of them.
+    fn sparkline(&self, width: usize) -> String {
+        let start = self.history.len().saturating_sub(width);
+        let shown = &self.history[start..];
+        let highest = shown.iter().map(|stats| stats.population).max().unwrap_or(0);
+
+        shown
+            .iter()
+            .map(|stats| match highest {
+                0 => ' ',
+                _ if stats.population == 0 => ' ',
+                _ => SPARKS[stats.population * (SPARKS.len() - 1) / highest],
+            })
+            .collect()
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("generation,population,births,deaths,top,left,bottom,right,density\n");
+
+        for stats in &self.history {
+            let bounding_box = match stats.bounding_box {
+                Some((top, left, bottom, right)) => format!("{top},{left},{bottom},{right}"),
+                None => String::from(",,,"),
+            };
+            csv.push_str(&format!(
+                "{},{},{},{},{},{:.4}\n",
+                stats.generation, stats.population, stats.births, stats.deaths, bounding_box, stats.density
+            ));
+        }
+        csv
+    }
+
+    pub fn save_csv(&self, filename: &str) -> String {
+        if self.history.is_empty() {
+            return String::from("[-] No statistics yet. Play a game first.");
+        }
+
+        match std::fs::write(filename, self.to_csv()) {
+            Ok(_) => format!("[+] Statistics of {} generations saved to {filename}.", self.history.len()),
+            Err(_) => String::from("[-] Failed to save statistics."),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::next_generation;
+    use crate::rule::Rule;
+
+    #[test]
+    fn births_deaths_and_box() {
+        // A blinker turning from flat to upright.
+        let mut map: Vectrix = vec![vec![Cell::dead(); 5]; 5];
+        map[2][1..4].fill(Cell::alive());
+        let mut statistics = Statistics::new();
+        statistics.record(0, None, &map);
+        let next = next_generation(&map, &Rule::life());
+        statistics.record(1, Some(&map), &next);
+
+        let last = statistics.history.last().unwrap();
+        assert_eq!((last.population, last.births, last.deaths), (3, 2, 2));
+        assert_eq!(last.bounding_box, Some((1, 2, 3, 2)));
+        assert_eq!(
+            statistics.to_csv(),
+            "generation,population,births,deaths,top,left,bottom,right,density\n\
+             0,3,0,0,2,1,2,3,0.1200\n\
+             1,3,2,2,1,2,3,2,0.1200\n"
+        );
+        assert_eq!(statistics.panel()[1], "Population: 3");
+    }
+
+    #[test]
+    fn empty_map_has_no_box() {
+        let mut statistics = Statistics::new();
+        statistics.record(0, None, &vec![vec![Cell::dead(); 3]; 3]);
+        assert_eq!(statistics.history[0].bounding_box, None);
+        assert!(statistics.to_csv().ends_with("0,0,0,0,,,,,0.0000\n"));
+        assert_eq!(statistics.panel()[3], "Box: none");
+    }
+
+    #[test]
+    fn panel_fits_big_maps() {
+        // "Box: 111 x 111 at (100, 100)" is longer than the panel.
+        let mut map: Vectrix = vec![vec![Cell::dead(); 250]; 250];
+        map[100][100] = Cell::alive();
+        map[210][210] = Cell::alive();
+        let mut statistics = Statistics::new();
+        statistics.record(0, None, &map);
+
+        let panel = statistics.panel();
+        assert!(panel.iter().any(|line| line.starts_with("Box: 111 x 111")));
+        assert!(panel.iter().all(|line| line.chars().count() <= PANEL_COLS));
+    }
+}
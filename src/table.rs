@@ -0,0 +1,576 @@
+// Rule tables: Golly `.rule` files.
+//
+// A rule table lists transitions one per line: the state of the cell, the
+// states of its neighbours and the state it becomes. For the Moore
+// neighbourhood that is C,N,NE,E,SE,S,SW,W,NW,C' and for von Neumann
+// C,N,E,S,W,C'. Cells that match no line keep their state.
+//
+//     @RULE WireWorld
+//     @TABLE
+//     n_states:4
+//     neighborhood:Moore
+//     symmetries:permute
+//     var a={0,1,2,3}
+//     1,a,a,a,a,a,a,a,a,2
+//
+// Variables stand for any of their states. A variable used once matches
+// any of them; used more than once in a line it must be the same state
+// everywhere, so the line is tried once per state. Symmetries add the
+// rotations and reflections of every line, so they don't have to be written
+// out. `permute` tables would need every order of the neighbours, up to 8!
+// of them, so they are looked up with the neighbours sorted instead.
+//
+// `@COLORS` lines give the colour of a state, `state red green blue`, or
+// a gradient from state 1 to the last state, `red green blue red green
+// blue`. Later lines win. Other sections like @ICONS are skipped.
+//
+// A few tables come with the game, from the `rules/` folder bundled by
+// build.rs. Others are loaded from a path ending in `.rule`.
+
+use std::collections::HashMap;
+
+use crate::rule::{Neighbourhood, RING};
+
+include!(concat!(env!("OUT_DIR"), "/rules.rs"));
+
+// States each input accepts, and the state the cell becomes.
+type Transition = (Vec<Vec<u8>>, u8);
+
+const VON_NEUMANN: [(i64, i64); 4] = [(-1, 0), (0, 1), (1, 0), (0, -1)];
+
+#[derive(Clone)]
+pub struct RuleTable {
+    // From @RULE.
+    pub name: String,
+    pub states: u8,
+    pub neighbourhood: Neighbourhood,
+    // Resulting state of every transition, once the symmetries are added.
+    outputs: Vec<u8>,
+    // masks[input][state] has a bit set for every transition that accepts
+    // `state` in `input` (0 is the cell itself, then the neighbours). The
+    // transitions a cell matches are the ones set in all its inputs' masks.
+    masks: Vec<Vec<Vec<u64>>>,
+    // `permute` tables instead: the next state by the state of the cell and
+    // then its neighbours' states, sorted. The first line that matches wins.
+    permuted: Option<HashMap<Vec<u8>, u8>>,
+    colours: Vec<Option<(u8, u8, u8)>>,
+}
+
+// What `next_state` works with, kept from cell to cell so looking up a cell
+// doesn't allocate anything.
+#[derive(Default)]
+pub struct Lookup {
+    // The state of the cell and then of its neighbours.
+    pub inputs: Vec<u8>,
+    matching: Vec<u64>,
+}
+
+impl RuleTable {
+    // `name` is a path to a .rule file, or the name of a bundled one.
+    pub fn find(name: &str) -> Result<RuleTable, String> {
+        if name.ends_with(".rule") {
+            let text = std::fs::read_to_string(name).map_err(|_| format!("can't read {name}"))?;
+            return RuleTable::parse(&text);
+        }
+
+        for (file_name, text) in BUNDLED_RULES {
+            let table = RuleTable::parse(text).map_err(|error| format!("{file_name}: {error}"))?;
+            let stem = file_name.trim_end_matches(".rule");
+            if name.eq_ignore_ascii_case(stem) || name.eq_ignore_ascii_case(&table.name) {
+                return Ok(table);
+            }
+        }
+        Err(format!("no rule table called `{name}`"))
+    }
+
+    pub fn parse(text: &str) -> Result<RuleTable, String> {
+        let mut name = String::new();
+        let mut section = "";
+        let mut states: Option<u32> = None;
+        let mut neighbourhood = Neighbourhood::Moore;
+        let mut symmetries = "none";
+        let mut variables = Vec::<(String, Vec<u8>)>::new();
+        // Transition lines, split into states and variables.
+        let mut lines = Vec::<Vec<String>>::new();
+        // First and last state, and their colours. One state has the same
+        // colour at both ends.
+        let mut colours = Vec::<(u32, u32, (u8, u8, u8), (u8, u8, u8))>::new();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let at_line = |error: String| format!("line {}: {error}", number + 1);
+
+            if let Some(header) = line.strip_prefix('@') {
+                let (title, rest) = header.split_once(' ').unwrap_or((header, ""));
+                section = match title {
+                    "RULE" => {
+                        name = rest.trim().to_string();
+                        "RULE"
+                    },
+                    "TABLE" => "TABLE",
+                    "COLORS" => "COLORS",
+                    "TREE" => return Err(String::from("@TREE rules are not supported, only @TABLE")),
+                    _ => "",
+                };
+                continue;
+            }
+
+            match section {
+                "TABLE" => {
+                    if let Some(value) = line.strip_prefix("n_states:") {
+                        states = value.trim().parse::<u32>().ok();
+                    } else if let Some(value) = line
+                        .strip_prefix("neighborhood:")
+                        .or_else(|| line.strip_prefix("neighbourhood:"))
+                    {
+                        neighbourhood = match value.trim().to_lowercase().as_str() {
+                            "moore" => Neighbourhood::Moore,
+                            "vonneumann" => Neighbourhood::VonNeumann,
+                            other => return Err(at_line(format!("the `{other}` neighbourhood is not supported"))),
+                        };
+                    } else if let Some(value) = line.strip_prefix("symmetries:") {
+                        symmetries = value.trim();
+                    } else if let Some(definition) = line.strip_prefix("var ") {
+                        let variable = parse_variable(definition, &variables).map_err(at_line)?;
+                        variables.push(variable);
+                    } else {
+                        // Commas, or one character per state like 0123...
+                        let tokens: Vec<String> = match line.contains(',') {
+                            true => line.split(',').map(|token| token.trim().to_string()).collect(),
+                            false => line.chars().filter(|ch| !ch.is_whitespace()).map(String::from).collect(),
+                        };
+                        lines.push(tokens);
+                    }
+                },
+                "COLORS" => {
+                    let Ok(numbers) = line.split_whitespace().map(|n| n.parse::<u32>()).collect::<Result<Vec<u32>, _>>() else {
+                        // Something this game doesn't know, like a colour name.
+                        continue;
+                    };
+                    let rgb = |numbers: &[u32]| -> Result<(u8, u8, u8), String> {
+                        match numbers.iter().map(|n| u8::try_from(*n)).collect::<Result<Vec<u8>, _>>() {
+                            Ok(rgb) => Ok((rgb[0], rgb[1], rgb[2])),
+                            Err(_) => Err(at_line(String::from("red, green and blue go from 0 to 255"))),
+                        }
+                    };
+                    match numbers.len() {
+                        4 => {
+                            let colour = rgb(&numbers[1..])?;
+                            colours.push((numbers[0], numbers[0], colour, colour));
+                        },
+                        // The last state isn't known yet.
+                        6 => colours.push((1, u32::MAX, rgb(&numbers[..3])?, rgb(&numbers[3..])?)),
+                        _ => (),
+                    }
+                },
+                _ => (),
+            }
+        }
+
+        let states = match states {
+            Some(states) if (2..=crate::rule::MAX_STATES).contains(&states) => states as u8,
+            _ => return Err(format!("n_states must be 2 to {}", crate::rule::MAX_STATES)),
+        };
+        if name.is_empty() {
+            return Err(String::from("the file has no @RULE name"));
+        }
+
+        let inputs = neighbours(&neighbourhood).len() + 1;
+        let orders = symmetry_orders(symmetries, inputs - 1)?;
+
+        let mut transitions = Vec::<Transition>::new();
+        let mut permuted = orders.is_none().then(HashMap::<Vec<u8>, u8>::new);
+        for tokens in &lines {
+            if tokens.len() != inputs + 1 {
+                return Err(format!("`{}` should have {} states", tokens.join(","), inputs + 1));
+            }
+            for (sets, output) in expand(tokens, &variables, states)? {
+                let Some(orders) = &orders else {
+                    let permuted = permuted.as_mut().unwrap();
+                    for neighbours in multisets(&sets[1..]) {
+                        for cell in &sets[0] {
+                            let mut key = vec![*cell];
+                            key.extend(&neighbours);
+                            permuted.entry(key).or_insert(output);
+                        }
+                    }
+                    continue;
+                };
+                // The cell itself doesn't move, only its neighbours.
+                let mut seen = Vec::<Vec<Vec<u8>>>::new();
+                let variants = orders.iter().map(|order| {
+                    let mut moved = vec![sets[0].clone()];
+                    moved.extend(order.iter().map(|n| sets[n + 1].clone()));
+                    moved
+                });
+                for variant in variants {
+                    if !seen.contains(&variant) {
+                        seen.push(variant.clone());
+                        transitions.push((variant, output));
+                    }
+                }
+            }
+        }
+
+        let words = transitions.len().div_ceil(64);
+        let mut masks = vec![vec![vec![0u64; words]; states as usize]; inputs];
+        for (index, (sets, _)) in transitions.iter().enumerate() {
+            for (input, set) in sets.iter().enumerate() {
+                for state in set {
+                    masks[input][*state as usize][index / 64] |= 1 << (index % 64);
+                }
+            }
+        }
+
+        let mut table_colours = vec![None; states as usize];
+        for (first, last, from, to) in colours {
+            let last = last.min(states as u32 - 1);
+            for state in first..=last {
+                if let Some(slot) = table_colours.get_mut(state as usize) {
+                    *slot = Some(blend(from, to, state - first, last - first));
+                }
+            }
+        }
+
+        Ok(RuleTable {
+            name,
+            states,
+            neighbourhood,
+            outputs: transitions.iter().map(|(_, output)| *output).collect(),
+            masks,
+            permuted,
+            colours: table_colours,
+        })
+    }
+
+    // (rows, cols) of the neighbours, in the order the table lists them.
+    pub fn offsets(&self) -> &[(i64, i64)] {
+        neighbours(&self.neighbourhood)
+    }
+
+    // The next state of the cell in `lookup.inputs`. The neighbours may be
+    // put in another order.
+    pub fn next_state(&self, lookup: &mut Lookup) -> u8 {
+        let inputs = &mut lookup.inputs;
+        if let Some(permuted) = &self.permuted {
+            inputs[1..].sort_unstable();
+            return permuted.get(inputs.as_slice()).copied().unwrap_or(inputs[0]);
+        }
+
+        let matching = &mut lookup.matching;
+        matching.clear();
+        matching.resize(self.outputs.len().div_ceil(64), u64::MAX);
+        for (input, state) in inputs.iter().enumerate() {
+            match self.masks[input].get(*state as usize) {
+                Some(mask) => {
+                    for (word, bits) in matching.iter_mut().zip(mask) {
+                        *word &= bits;
+                    }
+                },
+                // A state the table doesn't have matches nothing.
+                None => return inputs[0],
+            }
+        }
+
+        for (word, bits) in matching.iter().enumerate() {
+            if *bits != 0 {
+                let index = word * 64 + bits.trailing_zeros() as usize;
+                return self.outputs[index];
+            }
+        }
+        inputs[0]
+    }
+
+    pub fn colour(&self, state: u8) -> Option<(u8, u8, u8)> {
+        self.colours.get(state as usize).copied().flatten()
+    }
+}
+
+// The colour `step` steps of `steps` from `from` to `to`.
+fn blend(from: (u8, u8, u8), to: (u8, u8, u8), step: u32, steps: u32) -> (u8, u8, u8) {
+    if steps == 0 {
+        return from;
+    }
+    let mix = |a: u8, b: u8| (a as i64 + (b as i64 - a as i64) * step as i64 / steps as i64) as u8;
+    (mix(from.0, to.0), mix(from.1, to.1), mix(from.2, to.2))
+}
+
+fn neighbours(neighbourhood: &Neighbourhood) -> &'static [(i64, i64)] {
+    match neighbourhood {
+        Neighbourhood::VonNeumann => &VON_NEUMANN,
+        _ => &RING,
+    }
+}
+
+// `b={0,1,2}`, `b={a,3}` or `b=a`.
+fn parse_variable(definition: &str, variables: &[(String, Vec<u8>)]) -> Result<(String, Vec<u8>), String> {
+    let Some((name, values)) = definition.split_once('=') else {
+        return Err(format!("`var {definition}` needs a `=`"));
+    };
+    let values = values.trim().trim_start_matches('{').trim_end_matches('}');
+
+    let mut states = Vec::<u8>::new();
+    for value in values.split(',') {
+        for state in token_states(value.trim(), variables)? {
+            if !states.contains(&state) {
+                states.push(state);
+            }
+        }
+    }
+    states.sort();
+    Ok((name.trim().to_string(), states))
+}
+
+// A number, or the states of a variable.
+fn token_states(token: &str, variables: &[(String, Vec<u8>)]) -> Result<Vec<u8>, String> {
+    if let Ok(state) = token.parse::<u8>() {
+        return Ok(vec![state]);
+    }
+    match variables.iter().find(|(name, _)| name == token) {
+        Some((_, states)) => Ok(states.clone()),
+        None => Err(format!("unknown variable `{token}`")),
+    }
+}
+
+// Replaces the variables that appear more than once by each of their
+// states, one transition per combination.
+fn expand(tokens: &[String], variables: &[(String, Vec<u8>)], states: u8) -> Result<Vec<Transition>, String> {
+    let is_variable = |token: &String| token.parse::<u8>().is_err();
+    let mut bound = Vec::<&String>::new();
+    for token in tokens.iter().filter(|token| is_variable(token)) {
+        if !bound.contains(&token) && tokens.iter().filter(|other| *other == token).count() > 1 {
+            bound.push(token);
+        }
+    }
+
+    let output = &tokens[tokens.len() - 1];
+    if is_variable(output) && !bound.contains(&output) {
+        return Err(format!("the output `{output}` must also be one of the inputs"));
+    }
+
+    // Every combination of states for the bound variables.
+    let mut combinations: Vec<Vec<u8>> = vec![vec![]];
+    for variable in &bound {
+        let values = token_states(variable, variables)?;
+        combinations = combinations
+            .iter()
+            .flat_map(|combination| {
+                values.iter().map(move |value| {
+                    let mut longer = combination.clone();
+                    longer.push(*value);
+                    longer
+                })
+            })
+            .collect();
+    }
+
+    let mut transitions = Vec::<Transition>::new();
+    for combination in combinations {
+        let mut sets = Vec::<Vec<u8>>::new();
+        for token in tokens {
+            let set = match bound.iter().position(|variable| *variable == token) {
+                Some(n) => vec![combination[n]],
+                None => token_states(token, variables)?,
+            };
+            if let Some(state) = set.iter().find(|state| **state >= states) {
+                return Err(format!("state {state} is past the {states} states of the table"));
+            }
+            sets.push(set);
+        }
+        let output = sets.pop().unwrap()[0];
+        transitions.push((sets, output));
+    }
+    Ok(transitions)
+}
+
+// The orders the neighbours can be read in for a symmetry, None for
+// `permute`. With 8 neighbours a quarter turn moves them 2 places around
+// the ring, with 4 neighbours 1 place.
+fn symmetry_orders(symmetries: &str, count: usize) -> Result<Option<Vec<Vec<usize>>>, String> {
+    let quarter = count / 4;
+    let (step, reflect) = match symmetries {
+        "none" => (count, false),
+        "rotate4" => (quarter, false),
+        "rotate4reflect" => (quarter, true),
+        "rotate8" if count == 8 => (1, false),
+        "rotate8reflect" if count == 8 => (1, true),
+        "reflect" => (count, true),
+        "permute" => return Ok(None),
+        _ => return Err(format!("symmetries `{symmetries}` don't work here")),
+    };
+
+    let mut orders = Vec::<Vec<usize>>::new();
+    for turn in (0..count).step_by(step) {
+        let rotated: Vec<usize> = (0..count).map(|n| (n + turn) % count).collect();
+        if reflect {
+            // Left and right swap, the top stays.
+            orders.push((0..count).map(|n| rotated[(count - n) % count]).collect());
+        }
+        orders.push(rotated);
+    }
+    Ok(Some(orders))
+}
+
+// Every way of taking one state from each set, sorted, each way once.
+fn multisets(sets: &[Vec<u8>]) -> Vec<Vec<u8>> {
+    let mut all: Vec<Vec<u8>> = vec![vec![]];
+    for set in sets {
+        let mut longer = Vec::<Vec<u8>>::new();
+        for multiset in &all {
+            for state in set {
+                let mut added = multiset.clone();
+                added.insert(added.partition_point(|other| other <= state), *state);
+                longer.push(added);
+            }
+        }
+        longer.sort();
+        longer.dedup();
+        all = longer;
+    }
+    all
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(line: &str) -> Vec<String> {
+        line.split(',').map(String::from).collect()
+    }
+
+    fn next(table: &RuleTable, inputs: &[u8]) -> u8 {
+        let mut lookup = Lookup::default();
+        lookup.inputs.extend(inputs);
+        table.next_state(&mut lookup)
+    }
+
+    #[test]
+    fn every_bundled_table_parses() {
+        for (file_name, text) in BUNDLED_RULES {
+            if let Err(error) = RuleTable::parse(text) {
+                panic!("{file_name}: {error}");
+            }
+        }
+    }
+
+    #[test]
+    fn wireworld_electrons() {
+        let table = RuleTable::find("WireWorld").unwrap();
+        assert_eq!(table.states, 4);
+        // Head to tail, tail to wire.
+        assert_eq!(next(&table, &[1, 0, 0, 0, 0, 0, 0, 0, 0]), 2);
+        assert_eq!(next(&table, &[2, 3, 3, 0, 0, 0, 0, 0, 0]), 3);
+        // Wire with one or two heads around becomes a head, in any place
+        // thanks to `permute`.
+        assert_eq!(next(&table, &[3, 0, 0, 0, 0, 0, 1, 0, 0]), 1);
+        assert_eq!(next(&table, &[3, 3, 1, 0, 2, 0, 0, 1, 0]), 1);
+        // Three heads is too many, nothing matches and it stays wire.
+        assert_eq!(next(&table, &[3, 1, 1, 1, 0, 0, 0, 0, 0]), 3);
+        assert_eq!(table.colour(3), Some((255, 128, 0)));
+    }
+
+    #[test]
+    fn bad_tables_are_errors() {
+        let table = "@RULE Test\n@TABLE\nn_states:2\nneighborhood:vonNeumann\n";
+        assert!(RuleTable::parse(&format!("{table}0,1,0,0,0,1\n")).is_ok());
+        assert!(RuleTable::parse(&format!("{table}0,1,0,0,1\n")).is_err());
+        assert!(RuleTable::parse(&format!("{table}0,1,0,0,0,2\n")).is_err());
+        assert!(RuleTable::parse(&format!("{table}symmetries:rotate8\n")).is_err());
+        assert!(RuleTable::parse("@TABLE\nn_states:2\n").is_err());
+        assert!(RuleTable::parse("@RULE Tree\n@TREE\n").is_err());
+    }
+
+    #[test]
+    fn langtons_loops_rotate() {
+        let table = RuleTable::find("Langtons-Loops").unwrap();
+        assert_eq!(table.states, 8);
+        // 000012 is written with the 1 on the west, rotate4 turns it around.
+        for inputs in [[0, 0, 0, 0, 1], [0, 1, 0, 0, 0], [0, 0, 1, 0, 0], [0, 0, 0, 1, 0]] {
+            assert_eq!(next(&table, &inputs), 2);
+        }
+        // Nothing written for it, so the cell keeps its state.
+        assert_eq!(next(&table, &[3, 3, 3, 3, 3]), 3);
+    }
+
+    #[test]
+    fn expand_binds_repeated_variables() {
+        let variables = vec![(String::from("a"), vec![0, 1]), (String::from("b"), vec![0, 1, 2])];
+        let transitions = expand(&tokens("0,a,b,a,0,a"), &variables, 3).unwrap();
+        // `a` is used three times so it is the same state everywhere, `b`
+        // once so it is any of its states.
+        assert_eq!(
+            transitions,
+            vec![
+                (vec![vec![0], vec![0], vec![0, 1, 2], vec![0], vec![0]], 0),
+                (vec![vec![0], vec![1], vec![0, 1, 2], vec![1], vec![0]], 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn expand_rejects_bad_lines() {
+        let variables = vec![(String::from("a"), vec![0, 1]), (String::from("b"), vec![0, 1, 2])];
+        // The output can't be a variable that isn't bound.
+        assert!(expand(&tokens("0,a,0,0,0,b"), &variables, 3).is_err());
+        assert!(expand(&tokens("0,0,0,0,0,c"), &variables, 3).is_err());
+        assert!(expand(&tokens("0,b,0,0,0,1"), &variables, 2).is_err());
+    }
+
+    #[test]
+    fn symmetry_orders_turn_and_reflect() {
+        let orders = symmetry_orders("rotate4", 4).unwrap().unwrap();
+        assert_eq!(orders, vec![vec![0, 1, 2, 3], vec![1, 2, 3, 0], vec![2, 3, 0, 1], vec![3, 0, 1, 2]]);
+
+        // A quarter turn of the Moore ring is 2 places.
+        let orders = symmetry_orders("rotate4", 8).unwrap().unwrap();
+        assert_eq!(orders.len(), 4);
+        assert_eq!(orders[1], vec![2, 3, 4, 5, 6, 7, 0, 1]);
+
+        // North stays, east and west swap.
+        let orders = symmetry_orders("reflect", 4).unwrap().unwrap();
+        assert_eq!(orders, vec![vec![0, 3, 2, 1], vec![0, 1, 2, 3]]);
+
+        assert_eq!(symmetry_orders("rotate8reflect", 8).unwrap().unwrap().len(), 16);
+        assert_eq!(symmetry_orders("none", 8).unwrap().unwrap(), vec![(0..8).collect::<Vec<usize>>()]);
+        assert!(symmetry_orders("permute", 8).unwrap().is_none());
+        assert!(symmetry_orders("rotate8", 4).is_err());
+        assert!(symmetry_orders("rotate6", 8).is_err());
+    }
+
+    #[test]
+    fn multisets_skip_repeats() {
+        assert_eq!(multisets(&[vec![1], vec![2], vec![1]]), vec![vec![1, 1, 2]]);
+        assert_eq!(multisets(&[vec![0, 1], vec![0, 1]]), vec![vec![0, 0], vec![0, 1], vec![1, 1]]);
+        // 8 neighbours of any of 4 states: 165 multisets, not 4^8 orders.
+        assert_eq!(multisets(&vec![vec![0, 1, 2, 3]; 8]).len(), 165);
+    }
+
+    #[test]
+    fn permute_matches_any_order() {
+        let table = "@RULE Test\n@TABLE\nn_states:3\nneighborhood:Moore\nsymmetries:permute\nvar a={0,1,2}\n";
+        // The first line that matches wins, like in the other tables.
+        let parsed = RuleTable::parse(&format!("{table}0,1,2,0,0,0,0,0,0,2\n0,1,a,0,0,0,0,0,0,1\n")).unwrap();
+        assert_eq!(next(&parsed, &[0, 0, 0, 2, 0, 0, 0, 1, 0]), 2);
+        assert_eq!(next(&parsed, &[0, 0, 0, 0, 0, 0, 0, 1, 1]), 1);
+        assert_eq!(next(&parsed, &[0, 1, 1, 1, 0, 0, 0, 0, 0]), 0);
+        // The cell itself isn't moved around.
+        assert_eq!(next(&parsed, &[1, 0, 0, 0, 0, 0, 0, 2, 0]), 1);
+    }
+
+    #[test]
+    fn colours_and_gradients() {
+        let table = "@RULE Test\n@TABLE\nn_states:4\nneighborhood:vonNeumann\n0,1,0,0,0,1\n@COLORS\n";
+        let parsed = RuleTable::parse(&format!("{table}0 10 20 30\n0 0 0 255 0 0\n3 1 2 3\nwhite\n")).unwrap();
+        assert_eq!(parsed.colour(0), Some((10, 20, 30)));
+        // The gradient goes over states 1 to 3, then 3 gets its own colour.
+        assert_eq!(parsed.colour(1), Some((0, 0, 0)));
+        assert_eq!(parsed.colour(2), Some((127, 0, 0)));
+        assert_eq!(parsed.colour(3), Some((1, 2, 3)));
+
+        assert!(RuleTable::parse(&format!("{table}1 300 0 0\n")).is_err());
+        assert!(RuleTable::parse(&format!("{table}0 0 0 255 0 256\n")).is_err());
+    }
+}
@@ -0,0 +1,237 @@
+// Two-player game.
+//
+// Red and Blue take turns placing a few cells each on the same map, then the
+// universe runs for some generations with the Immigration colours, so every
+// newborn belongs to the player with more parents around it. After every
+// round each player scores their live cells plus their territory: the dead
+// cells where the last live cell was theirs. The higher score after the last round
+// wins, unless a player is wiped out before that.
+
+use crate::{CELL_COLOURS, Cell, Vectrix};
+
+// Colours of the players, see CELL_COLOURS.
+pub const PLAYERS: [u8; 2] = [1, 2];
+
+pub struct Settings {
+    pub rounds: u32,
+    pub cells_per_turn: u32,
+    pub generations_per_round: u32,
+}
+
+pub struct RoundScore {
+    pub round: u32,
+    // One per player, in the order of PLAYERS.
+    pub population: [usize; 2],
+    pub territory: [usize; 2],
+}
+
+impl RoundScore {
+    pub fn total(&self, player: usize) -> usize {
+        self.population[player] + self.territory[player]
+    }
+}
+
+pub enum Outcome {
+    // Player index, and how they won.
+    Winner(usize, String),
+    Draw,
+}
+
+pub struct Versus {
+    pub settings: Settings,
+    // Colour of the last live cell seen on every cell, 0 for never.
+    owner: Vec<Vec<u8>>,
+    pub scores: Vec<RoundScore>,
+}
+
+impl Versus {
+    pub fn new(settings: Settings, map: &Vectrix) -> Versus {
+        Versus {
+            settings,
+            owner: vec![vec![0; map[0].len()]; map.len()],
+            scores: Vec::new(),
+        }
+    }
+
+    pub fn player_name(player: usize) -> &'static str {
+        CELL_COLOURS[PLAYERS[player] as usize - 1].0
+    }
+
+    // Call after placing cells and after every generation.
+    pub fn record(&mut self, map: &Vectrix) {
+        for (i, row) in map.iter().enumerate() {
+            for (j, cell) in row.iter().enumerate() {
+                if let Cell::Alive(colour, _) = cell
+                    && PLAYERS.contains(colour)
+                {
+                    self.owner[i][j] = *colour;
+                }
+            }
+        }
+    }
+
+    // Call at the end of every round.
+    pub fn score_round(&mut self, map: &Vectrix) -> &RoundScore {
+        let mut population = [0; 2];
+        let mut territory = [0; 2];
+        for (player, colour) in PLAYERS.iter().enumerate() {
+            population[player] = map.iter().flatten().filter(|cell| cell.colour() == *colour).count();
+            // Live cells are scored above, only the land they left counts here.
+            territory[player] = map
+                .iter()
+                .flatten()
+                .zip(self.owner.iter().flatten())
+                .filter(|(cell, owner)| !matches!(cell, Cell::Alive(..)) && *owner == colour)
+                .count();
+        }
+
+        self.scores.push(RoundScore {
+            round: self.scores.len() as u32 + 1,
+            population,
+            territory,
+        });
+        self.scores.last().unwrap()
+    }
+
+    // All rounds played, or only one player has cells left.
+    pub fn is_over(&self) -> bool {
+        self.scores.len() as u32 >= self.settings.rounds || self.wiped_out().is_some()
+    }
+
+    // The player with no live cells while the other one has some.
+    fn wiped_out(&self) -> Option<usize> {
+        let last = self.scores.last()?;
+        match last.population {
+            [0, other] if other > 0 => Some(0),
+            [other, 0] if other > 0 => Some(1),
+            _ => None,
+        }
+    }
+
+    pub fn outcome(&self) -> Outcome {
+        let Some(last) = self.scores.last() else {
+            return Outcome::Draw;
+        };
+        if let Some(loser) = self.wiped_out() {
+            let reason = format!("{} was wiped out in round {}", Versus::player_name(loser), last.round);
+            return Outcome::Winner(1 - loser, reason);
+        }
+
+        let (red, blue) = (last.total(0), last.total(1));
+        match red.cmp(&blue) {
+            std::cmp::Ordering::Greater => Outcome::Winner(0, format!("{red} to {blue}")),
+            std::cmp::Ordering::Less => Outcome::Winner(1, format!("{blue} to {red}")),
+            std::cmp::Ordering::Equal => Outcome::Draw,
+        }
+    }
+
+    pub fn describe_outcome(&self) -> String {
+        match self.outcome() {
+            Outcome::Winner(player, reason) => format!("{} wins, {reason}.", Versus::player_name(player)),
+            Outcome::Draw => String::from("It's a draw."),
+        }
+    }
+
+    // Table of every round and the result.
+    pub fn summary(&self) -> Vec<String> {
+        let (red, blue) = (Versus::player_name(0), Versus::player_name(1));
+        let mut lines = vec![
+            format!("Round | {red:>6} cells | {red:>6} land | {blue:>6} cells | {blue:>6} land"),
+            String::from("------+--------------+-------------+--------------+------------"),
+        ];
+        for score in &self.scores {
+            lines.push(format!(
+                "{:>5} | {:>12} | {:>11} | {:>12} | {:>11}",
+                score.round, score.population[0], score.territory[0], score.population[1], score.territory[1]
+            ));
+        }
+        if let Some(last) = self.scores.last() {
+            lines.push(String::new());
+            lines.push(format!("Score (cells + land): {red} {}, {blue} {}", last.total(0), last.total(1)));
+        }
+        lines.push(self.describe_outcome());
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_game(rounds: u32) -> (Versus, Vectrix) {
+        let map = vec![vec![Cell::dead(); 4]; 4];
+        let settings = Settings {
+            rounds,
+            cells_per_turn: 3,
+            generations_per_round: 1,
+        };
+        (Versus::new(settings, &map), map)
+    }
+
+    #[test]
+    fn live_cells_are_not_land() {
+        let (mut game, mut map) = new_game(3);
+        map[0][0] = Cell::coloured(PLAYERS[0]);
+        map[0][1] = Cell::coloured(PLAYERS[0]);
+        map[3][3] = Cell::coloured(PLAYERS[1]);
+        game.record(&map);
+
+        // Red's cell at [0][1] dies and leaves land behind.
+        map[0][1] = Cell::dead();
+        game.record(&map);
+        let score = game.score_round(&map);
+        assert_eq!(score.population, [1, 1]);
+        assert_eq!(score.territory, [1, 0]);
+        assert_eq!((score.total(0), score.total(1)), (2, 1));
+    }
+
+    #[test]
+    fn outcome_by_score_or_wipe_out() {
+        let (mut game, mut map) = new_game(2);
+        map[0][0] = Cell::coloured(PLAYERS[0]);
+        map[3][3] = Cell::coloured(PLAYERS[1]);
+        game.record(&map);
+        game.score_round(&map);
+        assert!(!game.is_over());
+        assert!(matches!(game.outcome(), Outcome::Draw));
+
+        // Blue moves on and gets more land than Red.
+        map[3][3] = Cell::dead();
+        map[2][2] = Cell::coloured(PLAYERS[1]);
+        game.record(&map);
+        game.score_round(&map);
+        assert!(game.is_over());
+        assert!(matches!(game.outcome(), Outcome::Winner(1, _)));
+
+        // Red has no cells left.
+        let (mut game, mut map) = new_game(5);
+        map[0][0] = Cell::coloured(PLAYERS[1]);
+        game.record(&map);
+        game.score_round(&map);
+        assert!(game.is_over());
+        match game.outcome() {
+            Outcome::Winner(player, reason) => {
+                assert_eq!(player, 1);
+                assert_eq!(reason, "Red was wiped out in round 1");
+            },
+            Outcome::Draw => panic!("Red was wiped out"),
+        }
+    }
+
+    #[test]
+    fn summary_lists_every_round() {
+        let (mut game, mut map) = new_game(2);
+        map[0][0] = Cell::coloured(PLAYERS[0]);
+        map[3][3] = Cell::coloured(PLAYERS[1]);
+        game.record(&map);
+        game.score_round(&map);
+        game.score_round(&map);
+
+        let summary = game.summary();
+        // Header, rule, two rounds, a blank line, the score and the result.
+        assert_eq!(summary.len(), 7);
+        assert!(summary[2].starts_with("    1 |"));
+        assert!(summary[3].starts_with("    2 |"));
+        assert_eq!(summary[6], "It's a draw.");
+    }
+}